@@ -0,0 +1,14 @@
+/// Resolves on ctrl-c or, on unix, SIGTERM which is what orchestrators send before killing the process.
+pub async fn signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).unwrap().recv().await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate => {}
+    }
+}
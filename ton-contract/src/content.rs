@@ -0,0 +1,24 @@
+use toner::tlb::Cell;
+
+/// Decodes the URI of TEP-64 off-chain token content: a `0x01` prefix followed by a snake string.
+/// On-chain and semi-chain content is left to the caller.
+pub fn offchain_uri(content: &Cell) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut cell = content;
+    loop {
+        if cell.data.len() % 8 != 0 {
+            return None;
+        }
+        bytes.extend_from_slice(cell.data.as_raw_slice());
+
+        match cell.references.first() {
+            Some(next) => cell = next,
+            None => break,
+        }
+    }
+
+    match bytes.split_first() {
+        Some((0x01, uri)) => String::from_utf8(uri.to_vec()).ok(),
+        _ => None,
+    }
+}
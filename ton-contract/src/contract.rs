@@ -1,6 +1,10 @@
-use toner::ton::MsgAddress;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::sync::Arc;
+use toner::tlb::bits::de::unpack_bytes;
+use toner::tlb::{Cell, Error as TlbError};
+use toner::ton::{boc::BoC, MsgAddress};
 use tonlibjson_client::{
-    block::{SmcRunResult, TvmBoxedStackEntry},
+    block::{RawFullAccountState, SmcRunResult, TonBlockIdExt, TvmBoxedStackEntry},
     ton::TonClient,
 };
 
@@ -9,11 +13,23 @@ use crate::TonContractError;
 pub struct TonContract {
     address: MsgAddress,
     client: TonClient,
+    block_id: Option<TonBlockIdExt>,
 }
 
 impl TonContract {
     pub fn new(client: TonClient, address: MsgAddress) -> Self {
-        Self { client, address }
+        Self {
+            client,
+            address,
+            block_id: None,
+        }
+    }
+
+    /// Runs get-methods on `block_id` instead of the latest block, so several calls see the same state.
+    pub fn on_block(mut self, block_id: TonBlockIdExt) -> Self {
+        self.block_id = Some(block_id);
+
+        self
     }
 
     pub fn address(&self) -> MsgAddress {
@@ -29,19 +45,62 @@ impl TonContract {
         method: impl AsRef<str>,
         stack: Vec<TvmBoxedStackEntry>,
     ) -> Result<Vec<TvmBoxedStackEntry>, TonContractError> {
+        let address = self.address().to_base64_std();
+        let method = method.as_ref().to_string();
         let SmcRunResult {
             stack, exit_code, ..
-        } = self
-            .client
-            .run_get_method(
-                self.address().to_base64_std(),
-                method.as_ref().to_string(),
-                stack,
-            )
-            .await?;
+        } = match self.block_id {
+            Some(ref block_id) => {
+                self.client
+                    .run_get_method_on_block(address, method, stack, block_id.clone())
+                    .await?
+            }
+            None => self.client.run_get_method(address, method, stack).await?,
+        };
         Ok(match exit_code {
             0 | 1 => stack,
             _ => return Err(TonContractError::Contract(exit_code)),
         })
     }
+
+    /// Returns the code and data cells of an active account whose code hash is one of `code_hashes`.
+    pub async fn get_known_state(
+        &self,
+        code_hashes: &[[u8; 32]],
+    ) -> Result<Option<(Arc<Cell>, Arc<Cell>)>, TonContractError> {
+        if code_hashes.is_empty() {
+            return Ok(None);
+        }
+
+        let state = self
+            .client
+            .raw_get_account_state(&self.address().to_base64_std())
+            .await?;
+
+        known_state(&state, code_hashes)
+    }
+}
+
+pub(crate) fn known_state(
+    state: &RawFullAccountState,
+    code_hashes: &[[u8; 32]],
+) -> Result<Option<(Arc<Cell>, Arc<Cell>)>, TonContractError> {
+    if state.code.is_empty() || state.data.is_empty() {
+        return Ok(None);
+    }
+
+    let code = root(&state.code)?;
+    if !code_hashes.contains(&code.hash()) {
+        return Ok(None);
+    }
+
+    Ok(Some((code, root(&state.data)?)))
+}
+
+pub(crate) fn root(boc: &str) -> Result<Arc<Cell>, TonContractError> {
+    let boc: BoC = unpack_bytes(STANDARD.decode(boc)?)?;
+
+    boc.single_root()
+        .ok_or_else(|| TonContractError::TLB(TlbError::custom("single root")))
+        .cloned()
 }
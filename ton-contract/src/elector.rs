@@ -0,0 +1,119 @@
+use crate::{TonContract, TonContractError, TvmBoxedStackEntryExt};
+use async_trait::async_trait;
+use num_bigint::BigUint;
+use toner::ton::MsgAddress;
+use tonlibjson_client::block::{TvmBoxedStackEntry, TvmList, TvmStackEntryList, TvmStackEntryTuple, TvmTuple};
+
+pub const ELECTOR_ADDRESS: MsgAddress = MsgAddress {
+    workchain_id: -1,
+    address: [0x33; 32],
+};
+
+pub struct ElectionParticipant {
+    pub public_key: BigUint,
+    pub stake: BigUint,
+}
+
+pub struct ElectionData {
+    pub election_id: u64,
+    pub participants: Vec<ElectionParticipant>,
+}
+
+pub struct PastElection {
+    pub election_id: u64,
+    pub unfreeze_at: u64,
+    pub stake_held: u64,
+    pub total_stake: BigUint,
+    pub bonuses: BigUint,
+}
+
+#[async_trait]
+pub trait ElectorContract {
+    async fn get_election_data(&self) -> Result<ElectionData, TonContractError>;
+
+    async fn get_past_elections(&self) -> Result<Vec<PastElection>, TonContractError>;
+}
+
+#[async_trait]
+impl ElectorContract for TonContract {
+    async fn get_election_data(&self) -> Result<ElectionData, TonContractError> {
+        let [election_id] = self
+            .run_get_method("active_election_id", [].into())
+            .await?
+            .try_into()?;
+        let [participants] = self
+            .run_get_method("participant_list", [].into())
+            .await?
+            .try_into()?;
+
+        let TvmBoxedStackEntry::TvmStackEntryList(TvmStackEntryList {
+            list: TvmList { elements },
+        }) = participants
+        else {
+            return Err(TonContractError::InvalidStack);
+        };
+
+        let participants = elements
+            .into_iter()
+            .map(|participant| {
+                let TvmBoxedStackEntry::TvmStackEntryTuple(TvmStackEntryTuple {
+                    tuple: TvmTuple { elements },
+                }) = participant
+                else {
+                    return Err(TonContractError::InvalidStack);
+                };
+                let [public_key, stake] = elements.try_into()?;
+
+                Ok(ElectionParticipant {
+                    public_key: public_key.to_number()?,
+                    stake: stake.to_number()?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ElectionData {
+            election_id: election_id.to_number()?,
+            participants,
+        })
+    }
+
+    async fn get_past_elections(&self) -> Result<Vec<PastElection>, TonContractError> {
+        let [past_elections] = self
+            .run_get_method("past_elections", [].into())
+            .await?
+            .try_into()?;
+
+        let TvmBoxedStackEntry::TvmStackEntryList(TvmStackEntryList {
+            list: TvmList { elements },
+        }) = past_elections
+        else {
+            return Err(TonContractError::InvalidStack);
+        };
+
+        elements
+            .into_iter()
+            .map(|election| {
+                let TvmBoxedStackEntry::TvmStackEntryTuple(TvmStackEntryTuple {
+                    tuple: TvmTuple { elements },
+                }) = election
+                else {
+                    return Err(TonContractError::InvalidStack);
+                };
+                // [election_id, unfreeze_at, stake_held, vset_hash, frozen_dict, total_stake, bonuses, complaints]
+                let [election_id, unfreeze_at, stake_held, _vset_hash, _frozen_dict, total_stake, bonuses, ..] =
+                    &elements[..]
+                else {
+                    return Err(TonContractError::InvalidStack);
+                };
+
+                Ok(PastElection {
+                    election_id: election_id.to_number()?,
+                    unfreeze_at: unfreeze_at.to_number()?,
+                    stake_held: stake_held.to_number()?,
+                    total_stake: total_stake.to_number()?,
+                    bonuses: bonuses.to_number()?,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,7 +1,14 @@
 use crate::{TonContract, TonContractError, TvmBoxedStackEntryExt};
 use async_trait::async_trait;
 use num_bigint::BigUint;
-use toner::{tlb::r#as::Data, ton::MsgAddress};
+use std::sync::Arc;
+use toner::{
+    tlb::bits::de::BitReaderExt,
+    tlb::de::{CellDeserialize, CellParser, CellParserError},
+    tlb::r#as::{Data, Ref},
+    tlb::Cell,
+    ton::{currency::Grams, MsgAddress},
+};
 
 pub struct JettonWalletData {
     pub balance: BigUint,
@@ -10,9 +17,56 @@ pub struct JettonWalletData {
     // TODO: jetton_wallet_code
 }
 
+impl<'de> CellDeserialize<'de> for JettonWalletData {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        let balance = parser.unpack_as::<_, Grams>()?;
+        let owner = parser.unpack()?;
+        let master = parser.unpack()?;
+        let _jetton_wallet_code: Cell = parser.parse_as::<_, Ref>()?;
+
+        Ok(Self { balance, owner, master })
+    }
+}
+
+pub struct JettonMasterData {
+    pub total_supply: BigUint,
+    pub mintable: bool,
+    pub admin: MsgAddress,
+    pub content: Arc<Cell>,
+}
+
+#[async_trait]
+pub trait JettonMasterContract {
+    async fn get_jetton_data(&self) -> Result<JettonMasterData, TonContractError>;
+}
+
+#[async_trait]
+impl JettonMasterContract for TonContract {
+    async fn get_jetton_data(&self) -> Result<JettonMasterData, TonContractError> {
+        let [total_supply, mintable, admin, content, _jetton_wallet_code] = self
+            .run_get_method("get_jetton_data", [].into())
+            .await?
+            .try_into()?;
+
+        Ok(JettonMasterData {
+            total_supply: total_supply.to_number()?,
+            mintable: mintable.to_number::<i64>()? != 0,
+            admin: admin.parse_cell_fully_as::<_, Data>()?,
+            content: content.to_cell()?,
+        })
+    }
+}
+
 #[async_trait]
 pub trait JettonWalletContract {
     async fn get_wallet_data(&self) -> Result<JettonWalletData, TonContractError>;
+
+    /// Decodes the wallet data cell directly when the wallet code hash is one of `known_code_hashes`,
+    /// otherwise falls back to `get_wallet_data`. Returns whether the data was decoded from state.
+    async fn get_wallet_data_from_state(
+        &self,
+        known_code_hashes: &[[u8; 32]],
+    ) -> Result<(JettonWalletData, bool), TonContractError>;
 }
 
 #[async_trait]
@@ -29,4 +83,14 @@ impl JettonWalletContract for TonContract {
             master: master.parse_cell_fully_as::<_, Data>()?,
         })
     }
+
+    async fn get_wallet_data_from_state(
+        &self,
+        known_code_hashes: &[[u8; 32]],
+    ) -> Result<(JettonWalletData, bool), TonContractError> {
+        match self.get_known_state(known_code_hashes).await? {
+            Some((_, data)) => Ok((data.parse_fully()?, true)),
+            None => Ok((self.get_wallet_data().await?, false)),
+        }
+    }
 }
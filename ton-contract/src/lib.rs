@@ -4,5 +4,8 @@ mod error;
 
 pub use self::{adapters::*, contract::*, error::*};
 
+pub mod content;
+pub mod elector;
 pub mod jetton;
+pub mod nft;
 pub mod wallet;
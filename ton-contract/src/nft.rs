@@ -0,0 +1,105 @@
+use crate::{TonContract, TonContractError, TvmBoxedStackEntryExt};
+use async_trait::async_trait;
+use num_bigint::{BigInt, BigUint};
+use std::sync::Arc;
+use toner::{
+    tlb::bits::de::BitReaderExt,
+    tlb::de::{CellDeserialize, CellParser, CellParserError},
+    tlb::r#as::{Data, Ref},
+    tlb::Cell,
+    ton::MsgAddress,
+};
+
+pub struct NftItemData {
+    pub init: bool,
+    pub index: BigUint,
+    pub collection: MsgAddress,
+    pub owner: MsgAddress,
+    // TODO: individual_content
+}
+
+impl<'de> CellDeserialize<'de> for NftItemData {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        let index: u64 = parser.unpack()?;
+        let collection = parser.unpack()?;
+        let owner = parser.unpack()?;
+        let _individual_content: Cell = parser.parse_as::<_, Ref>()?;
+
+        Ok(Self {
+            init: true,
+            index: index.into(),
+            collection,
+            owner,
+        })
+    }
+}
+
+pub struct NftCollectionData {
+    /// -1 for collections with non-sequential indexes.
+    pub next_item_index: BigInt,
+    pub content: Arc<Cell>,
+    pub owner: MsgAddress,
+}
+
+#[async_trait]
+pub trait NftCollectionContract {
+    async fn get_collection_data(&self) -> Result<NftCollectionData, TonContractError>;
+}
+
+#[async_trait]
+impl NftCollectionContract for TonContract {
+    async fn get_collection_data(&self) -> Result<NftCollectionData, TonContractError> {
+        let [next_item_index, content, owner] = self
+            .run_get_method("get_collection_data", [].into())
+            .await?
+            .try_into()?;
+
+        Ok(NftCollectionData {
+            next_item_index: next_item_index.to_number()?,
+            content: content.to_cell()?,
+            owner: owner.parse_cell_fully_as::<_, Data>()?,
+        })
+    }
+}
+
+#[async_trait]
+pub trait NftItemContract {
+    async fn get_nft_data(&self) -> Result<NftItemData, TonContractError>;
+
+    /// Decodes the item data cell directly when the item code hash is one of `known_code_hashes`,
+    /// otherwise falls back to `get_nft_data`. Returns whether the data was decoded from state.
+    async fn get_nft_data_from_state(
+        &self,
+        known_code_hashes: &[[u8; 32]],
+    ) -> Result<(NftItemData, bool), TonContractError>;
+}
+
+#[async_trait]
+impl NftItemContract for TonContract {
+    async fn get_nft_data(&self) -> Result<NftItemData, TonContractError> {
+        let [init, index, collection, owner, _individual_content] = self
+            .run_get_method("get_nft_data", [].into())
+            .await?
+            .try_into()?;
+
+        Ok(NftItemData {
+            init: init.to_number::<i64>()? != 0,
+            index: index.to_number()?,
+            collection: collection.parse_cell_fully_as::<_, Data>()?,
+            owner: owner.parse_cell_fully_as::<_, Data>()?,
+        })
+    }
+
+    async fn get_nft_data_from_state(
+        &self,
+        known_code_hashes: &[[u8; 32]],
+    ) -> Result<(NftItemData, bool), TonContractError> {
+        let state = self.get_known_state(known_code_hashes).await?;
+
+        // items that are not initialized yet store only index and collection, leave them to the get method
+        match state.map(|(_, data)| data.parse_fully::<NftItemData>()) {
+            Some(Ok(data)) => Ok((data, true)),
+            _ => Ok((self.get_nft_data().await?, false)),
+        }
+    }
+}
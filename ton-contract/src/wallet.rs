@@ -1,10 +1,111 @@
 use async_trait::async_trait;
+use toner::{
+    tlb::bits::de::BitReaderExt,
+    tlb::de::{CellDeserialize, CellParser, CellParserError},
+};
+use tonlibjson_client::block::RawFullAccountState;
 
-use crate::{adapters::TvmBoxedStackEntryExt, TonContract, TonContractError};
+use crate::{
+    adapters::TvmBoxedStackEntryExt,
+    contract::{known_state, root},
+    TonContract, TonContractError,
+};
+
+/// Leading `seqno:uint32` of the wallet v3 and v4 data cell.
+struct WalletSeqno(u32);
+
+impl<'de> CellDeserialize<'de> for WalletSeqno {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        Ok(Self(parser.unpack()?))
+    }
+}
+
+/// `is_signature_allowed:Bool seqno:uint32` prefix of the wallet v5 data cell.
+struct WalletV5Seqno(u32);
+
+impl<'de> CellDeserialize<'de> for WalletV5Seqno {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        let _is_signature_allowed: bool = parser.unpack()?;
+
+        Ok(Self(parser.unpack()?))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletVersion {
+    V3R1,
+    V3R2,
+    V4R2,
+    V5R1,
+}
+
+impl WalletVersion {
+    pub const ALL: [Self; 4] = [Self::V3R1, Self::V3R2, Self::V4R2, Self::V5R1];
+
+    pub fn code_hash(&self) -> [u8; 32] {
+        match self {
+            Self::V3R1 => [
+                0xb6, 0x10, 0x41, 0xa5, 0x8a, 0x79, 0x80, 0xb9, 0x46, 0xe8, 0xfb, 0x9e, 0x19, 0x8e,
+                0x3c, 0x90, 0x4d, 0x24, 0x79, 0x9f, 0xfa, 0x36, 0x57, 0x4e, 0xa4, 0x25, 0x1c, 0x41,
+                0xa5, 0x66, 0xf5, 0x81,
+            ],
+            Self::V3R2 => [
+                0x84, 0xda, 0xfa, 0x44, 0x9f, 0x98, 0xa6, 0x98, 0x77, 0x89, 0xba, 0x23, 0x23, 0x58,
+                0x07, 0x2b, 0xc0, 0xf7, 0x6d, 0xc4, 0x52, 0x40, 0x02, 0xa5, 0xd0, 0x91, 0x8b, 0x9a,
+                0x75, 0xd2, 0xd5, 0x99,
+            ],
+            Self::V4R2 => [
+                0xfe, 0xb5, 0xff, 0x68, 0x20, 0xe2, 0xff, 0x0d, 0x94, 0x83, 0xe7, 0xe0, 0xd6, 0x2c,
+                0x81, 0x7d, 0x84, 0x67, 0x89, 0xfb, 0x4a, 0xe5, 0x80, 0xc8, 0x78, 0x86, 0x6d, 0x95,
+                0x9d, 0xab, 0xd5, 0xc0,
+            ],
+            Self::V5R1 => [
+                0x20, 0x83, 0x4b, 0x7b, 0x72, 0xb1, 0x12, 0x14, 0x7e, 0x1b, 0x2f, 0xb4, 0x57, 0xb8,
+                0x4e, 0x74, 0xd1, 0xa3, 0x0f, 0x04, 0xf7, 0x37, 0xd4, 0xf6, 0x2a, 0x66, 0x8e, 0x95,
+                0x52, 0xd2, 0xb7, 0x2f,
+            ],
+        }
+    }
+
+    pub fn from_code_hash(hash: &[u8; 32]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|version| &version.code_hash() == hash)
+    }
+
+    /// The name toncenter reports as `wallet_type`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::V3R1 => "wallet v3 r1",
+            Self::V3R2 => "wallet v3 r2",
+            Self::V4R2 => "wallet v4 r2",
+            Self::V5R1 => "wallet v5 r1",
+        }
+    }
+}
+
+pub struct WalletInformation {
+    pub state: RawFullAccountState,
+    /// `None` for uninitialized accounts and unknown code.
+    pub version: Option<WalletVersion>,
+    pub seqno: u32,
+}
 
 #[async_trait]
 pub trait WalletContract {
     async fn seqno(&self) -> Result<u32, TonContractError>;
+
+    /// Detects the wallet version by code hash and reads the seqno from the data cell,
+    /// accounts that aren't a known wallet have seqno 0.
+    async fn wallet_information(&self) -> Result<WalletInformation, TonContractError>;
+
+    /// Reads the seqno from the data cell when the wallet code hash is one of `known_code_hashes`,
+    /// otherwise falls back to `seqno`. Uninitialized wallets have seqno 0.
+    /// Returns whether the seqno was decoded from state.
+    async fn seqno_from_state(
+        &self,
+        known_code_hashes: &[[u8; 32]],
+    ) -> Result<(u32, bool), TonContractError>;
 }
 
 #[async_trait]
@@ -13,4 +114,52 @@ impl WalletContract for TonContract {
         let [seqno] = self.run_get_method("seqno", [].into()).await?.try_into()?;
         seqno.to_number()
     }
+
+    async fn wallet_information(&self) -> Result<WalletInformation, TonContractError> {
+        let state = self
+            .client()
+            .raw_get_account_state(&self.address().to_base64_std())
+            .await?;
+        if state.code.is_empty() || state.data.is_empty() {
+            return Ok(WalletInformation {
+                state,
+                version: None,
+                seqno: 0,
+            });
+        }
+
+        let version = WalletVersion::from_code_hash(&root(&state.code)?.hash());
+        let seqno = match version {
+            None => 0,
+            Some(WalletVersion::V5R1) => root(&state.data)?.parse::<WalletV5Seqno>()?.0,
+            Some(_) => root(&state.data)?.parse::<WalletSeqno>()?.0,
+        };
+
+        Ok(WalletInformation {
+            state,
+            version,
+            seqno,
+        })
+    }
+
+    async fn seqno_from_state(
+        &self,
+        known_code_hashes: &[[u8; 32]],
+    ) -> Result<(u32, bool), TonContractError> {
+        let state = self
+            .client()
+            .raw_get_account_state(&self.address().to_base64_std())
+            .await?;
+        if state.code.is_empty() {
+            return Ok((0, true));
+        }
+
+        match known_state(&state, known_code_hashes)? {
+            Some((code, data)) => match WalletVersion::from_code_hash(&code.hash()) {
+                Some(WalletVersion::V5R1) => Ok((data.parse::<WalletV5Seqno>()?.0, true)),
+                _ => Ok((data.parse::<WalletSeqno>()?.0, true)),
+            },
+            None => Ok((self.seqno().await?, false)),
+        }
+    }
 }
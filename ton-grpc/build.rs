@@ -2,13 +2,51 @@
 
 use std::env;
 use std::path::PathBuf;
+use std::process::Command;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    let git_sha = env::var("GIT_SHA").ok()
+        .or_else(|| Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|sha| sha.trim().to_owned()))
+        .unwrap_or_else(|| "unknown".to_owned());
+    println!("cargo:rustc-env=GIT_SHA={git_sha}");
+    if env::var("GIT_SHA").is_err() {
+        rerun_if_head_changed();
+    }
+
     tonic_build::configure()
         .file_descriptor_set_path(out_dir.join("ton_descriptor.bin"))
         .compile(&["proto/ton.proto"], &["proto"])?;
 
     Ok(())
 }
+
+// HEAD names the checked out branch, its ref file or packed-refs hold the commit it points at
+fn rerun_if_head_changed() {
+    let Some(git_dir) = Command::new("git")
+        .args(["rev-parse", "--git-dir"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|dir| PathBuf::from(dir.trim())) else { return };
+
+    let head = git_dir.join("HEAD");
+    let mut paths = vec![head.clone(), git_dir.join("packed-refs")];
+    if let Some(reference) = std::fs::read_to_string(&head).ok().as_deref().and_then(|head| head.trim().strip_prefix("ref: ")) {
+        paths.push(git_dir.join(reference));
+    }
+
+    // cargo reruns on every build for a path that doesn't exist
+    for path in paths.into_iter().filter(|path| path.exists()) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+}
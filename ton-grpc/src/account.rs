@@ -2,60 +2,98 @@
 
 use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::Arc;
 use tonic::{async_trait, Request, Response, Status};
 use tonlibjson_client::ton::TonClient;
 use anyhow::Result;
 use futures::{Stream, StreamExt, try_join, TryStreamExt, TryFutureExt};
 use derive_new::new;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use toner::tlb::bits::ser::pack_with;
+use toner::tlb::Cell;
+use toner::ton::boc::{BagOfCellsArgs, BoC};
+use toner::ton::MsgAddress;
+use ton_contract::{TonContract, TonContractError};
+use ton_contract::elector::{ElectorContract, ELECTOR_ADDRESS};
+use ton_contract::content;
+use ton_contract::jetton::{JettonMasterContract, JettonWalletContract};
+use ton_contract::nft::{NftCollectionContract, NftItemContract};
+use ton_contract::wallet::WalletContract;
 use tonlibjson_client::address::AccountAddressData;
 use tonlibjson_client::block::{RawFullAccountState, TonBlockIdExt, TvmCell};
-use crate::helpers::{extend_block_id, extend_from_tx_id, extend_to_tx_id};
+use tonlibjson_client::lookback::{Lookback, LookbackExceeded};
+use crate::drain::Drain;
+use crate::error;
+use crate::trace;
+use crate::helpers::{abi, address, extend_block_id, extend_from_tx_id, extend_to_tx_id, method_id, no_cache, tx, MAIN_CHAIN, MAIN_SHARD};
+use crate::helpers::workchain::AllowedWorkchains;
 use crate::ton::account_service_server::AccountService as BaseAccountService;
-use crate::ton::{GetAccountStateRequest, GetAccountStateResponse, GetAccountTransactionsRequest, GetShardAccountCellRequest, GetShardAccountCellResponse, Transaction};
+use crate::ton::{BlockId, GetAccountStateRequest, GetAccountStateResponse, GetAccountStatusRequest, GetAccountStatusResponse, GetAccountTransactionsRequest, AccountStatus, GetBalancesRequest, GetBalancesResponse, GetElectorStatsRequest, GetElectorStatsResponse, GetSeqnoRequest, GetSeqnoResponse, GetWalletInformationRequest, GetWalletInformationResponse, GetJettonWalletDataRequest, GetJettonWalletDataResponse, GetNftItemDataRequest, GetNftItemDataResponse, GetTokenDataRequest, GetTokenDataResponse, GetShardAccountCellRequest, GetShardAccountCellResponse, PackAddressRequest, PackAddressResponse, RunGetMethodBatchRequest, RunGetMethodBatchResponse, RunGetMethodRequest, RunGetMethodResponse, Transaction, TransactionFormat, UnpackAddressRequest, UnpackAddressResponse, ValidateAddressRequest, ValidateAddressResponse};
+use crate::ton::validate_address_response::FriendlyAddress;
 use crate::ton::get_account_state_response::AccountState;
-use crate::ton::{get_account_state_request, get_shard_account_cell_request};
+use crate::ton::get_token_data_response::Data;
+use crate::ton::{get_account_state_request, get_elector_stats_response, get_token_data_response, get_shard_account_cell_request, run_get_method_batch_response, run_get_method_request};
 use crate::ton::get_account_transactions_request::Order;
 
+const GET_METHOD_BATCH_MAX_CALLS: usize = 100;
+const GET_METHOD_BATCH_CONCURRENCY: usize = 8;
+const GET_BALANCES_MAX_ADDRESSES: usize = 1000;
+const GET_BALANCES_CONCURRENCY: usize = 16;
+
+#[derive(Default)]
+pub struct KnownCodeHashes {
+    pub jetton_wallet: Vec<[u8; 32]>,
+    pub nft_item: Vec<[u8; 32]>,
+    pub wallet: Vec<[u8; 32]>
+}
+
 #[derive(new)]
 pub struct AccountService {
-    client: TonClient
+    client: TonClient,
+    drain: Drain,
+    code_hashes: Arc<KnownCodeHashes>,
+    workchains: AllowedWorkchains
 }
 
 #[async_trait]
 impl BaseAccountService for AccountService {
     #[tracing::instrument(skip_all, err)]
     async fn get_account_state(&self, request: Request<GetAccountStateRequest>) -> std::result::Result<Response<GetAccountStateResponse>, Status> {
-        let msg = request.into_inner();
+        let no_cache = no_cache(&request);
+        let msg = trace::request(request.into_inner());
 
-        let address = AccountAddressData::from_str(&msg.account_address)
-            .map_err(|e| Status::internal(e.to_string()))?;
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
 
-        let state = self.fetch_account_state(&msg)
-            .map_err(|e| Status::internal(e.to_string()))
+        let state = self.fetch_account_state(&address.to_raw_string(), &msg, no_cache)
+            .map_err(error::too_deep_or_internal)
             .await?;
 
         let block_id = state.block_id.clone();
         let balance = state.balance.unwrap_or_default();
         let last_transaction_id = state.last_transaction_id.clone().map(|t| (&address, t).into());
-        let state: AccountState = state.into();
+        let status = AccountStatus::from(&state);
+        let state: AccountState = state.try_into()
+            .map_err(error::internal)?;
         let block_id = block_id.into();
 
-        Ok(Response::new(GetAccountStateResponse {
+        Ok(Response::new(trace::response(GetAccountStateResponse {
             balance,
             account_address: msg.account_address,
             block_id: Some(block_id),
             last_transaction_id,
-            account_state: Some(state)
-        }))
+            account_state: Some(state),
+            status: status.into()
+        })))
     }
 
     #[tracing::instrument(skip_all, err)]
     async fn get_shard_account_cell(&self, request: Request<GetShardAccountCellRequest>) -> Result<Response<GetShardAccountCellResponse>, Status> {
-        let msg = request.into_inner();
+        let msg = trace::request(request.into_inner());
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
 
-        let (block_id, cell) = self.fetch_shard_account_cell(&msg)
+        let (block_id, cell) = self.fetch_shard_account_cell(&address.to_raw_string(), &msg)
             .await
-            .map_err(|e| Status::internal(e.to_string()))?;
+            .map_err(error::internal)?;
 
         let block_id = block_id.into();
         let cell = cell.into();
@@ -66,96 +104,542 @@ impl BaseAccountService for AccountService {
             cell: Some(cell)
         };
 
-        Ok(Response::new(response))
+        Ok(Response::new(trace::response(response)))
     }
 
     type GetAccountTransactionsStream = Pin<Box<dyn Stream<Item=Result<Transaction, Status>> + Send + 'static>>;
 
     #[tracing::instrument(skip_all, err)]
     async fn get_account_transactions(&self, request: Request<GetAccountTransactionsRequest>) -> std::result::Result<Response<Self::GetAccountTransactionsStream>, Status> {
-        let msg = request.into_inner();
+        let permit = self.drain.acquire(request.remote_addr())?;
+        let msg = trace::request(request.into_inner());
         let client = self.client.clone();
 
-        let address = AccountAddressData::from_str(&msg.account_address)
-            .map_err(|e| Status::internal(e.to_string()))?;
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
+        let raw_address = address.to_raw_string();
 
         let (from_tx, to_tx) = try_join!(
-            extend_from_tx_id(&client, &msg.account_address, msg.from.clone()),
-            extend_to_tx_id(&client, &msg.account_address, msg.to.clone())
-        ).map_err(|e: anyhow::Error| Status::internal(e.to_string()))?;
+            extend_from_tx_id(&client, &raw_address, msg.from.clone()),
+            extend_to_tx_id(&client, &raw_address, msg.to.clone())
+        ).map_err(error::internal)?;
+
+        let lookback = Lookback { max_lt: msg.max_lookback_lt, max_count: msg.max_count.map(|c| c as usize) };
+        if lookback.max_lt.is_some() && msg.order() == Order::Unordered {
+            return Err(error::invalid_argument("max_lookback_lt", "max_lookback_lt requires FROM_NEW_TO_OLD order"));
+        }
 
         let stream = match msg.order() {
             Order::Unordered => {
-                client.get_account_tx_range_unordered(&msg.account_address, (from_tx, to_tx))
+                client.get_account_tx_range_unordered(&raw_address, (from_tx, to_tx))
                     .await
-                    .map_err(|e: anyhow::Error| Status::internal(e.to_string()))?
+                    .map_err(error::internal)?
                     .boxed()
             },
             Order::FromNewToOld => {
-                client.get_account_tx_range(&msg.account_address, (from_tx, to_tx))
+                client.get_account_tx_range(&raw_address, (from_tx, to_tx))
                     .boxed()
             }
-        }
-            .map_ok(move |t| (&address, t).into())
+        };
+
+        let full = msg.format() == TransactionFormat::Full;
+        let stream = lookback.limit(stream, |t| t.transaction_id.lt)
+            .map(move |t| {
+                let mut transaction: Transaction = (&address, t?).into();
+                if full {
+                    transaction.r#type = Some(tx::parse_type(&transaction.data)?.into());
+                }
+
+                Ok(transaction)
+            })
             .map_err(|e: anyhow::Error| {
+                if e.is::<LookbackExceeded>() {
+                    return Status::out_of_range(e.to_string());
+                }
+
                 tracing::error!(error = %e, "get_account_transactions failed");
-                Status::internal(e.to_string())
+                error::internal(e)
             })
             .boxed();
 
-        Ok(Response::new(stream))
+        Ok(Response::new(self.drain.wrap(permit, stream.map_ok(trace::response).boxed())))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn run_get_method(&self, request: Request<RunGetMethodRequest>) -> Result<Response<RunGetMethodResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let block_id = match &msg.at_block {
+            Some(block_id) => {
+                check_masterchain("at_block", block_id)?;
+
+                Some(extend_block_id(&self.client, block_id).await
+                    .map_err(error::internal)?)
+            },
+            None => None
+        };
+
+        Ok(Response::new(trace::response(self.call_get_method(msg, block_id).await?)))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn run_get_method_batch(&self, request: Request<RunGetMethodBatchRequest>) -> Result<Response<RunGetMethodBatchResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+        if msg.calls.len() > GET_METHOD_BATCH_MAX_CALLS {
+            return Err(error::invalid_argument("calls", format!("batch is limited to {} calls", GET_METHOD_BATCH_MAX_CALLS)));
+        }
+        if msg.calls.iter().any(|call| call.at_block.is_some()) {
+            return Err(error::invalid_argument("calls", "at_block must be set on the batch, not on calls"));
+        }
+        if let Some(block_id) = &msg.at_block {
+            check_masterchain("at_block", block_id)?;
+        }
+
+        let block_id = match &msg.at_block {
+            Some(block_id) => extend_block_id(&self.client, block_id).await,
+            None => self.client.get_masterchain_info().await.map(|info| info.last)
+        }.map_err(error::internal)?;
+
+        let results = futures::stream::iter(msg.calls)
+            .map(|call| self.call_get_method(call, Some(block_id.clone())))
+            .buffered(GET_METHOD_BATCH_CONCURRENCY)
+            .map(|result| run_get_method_batch_response::Item {
+                result: Some(match result {
+                    Ok(response) => run_get_method_batch_response::item::Result::Response(response),
+                    Err(status) => run_get_method_batch_response::item::Result::Error(run_get_method_batch_response::Error {
+                        code: status.code() as i32,
+                        message: status.message().to_owned()
+                    })
+                })
+            })
+            .collect()
+            .await;
+
+        Ok(Response::new(trace::response(RunGetMethodBatchResponse { results, block_id: Some(block_id.into()) })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_balances(&self, request: Request<GetBalancesRequest>) -> Result<Response<GetBalancesResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+        if msg.account_addresses.len() > GET_BALANCES_MAX_ADDRESSES {
+            return Err(error::invalid_argument("account_addresses", format!("balances are limited to {} addresses", GET_BALANCES_MAX_ADDRESSES)));
+        }
+        let addresses = msg.account_addresses.into_iter()
+            .map(|address| {
+                let raw_address = self.workchains.parse_address("account_addresses", &address)?.to_raw_string();
+
+                Ok((address, raw_address))
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+        if let Some(block_id) = &msg.at_block {
+            check_masterchain("at_block", block_id)?;
+        }
+
+        let block_id = match &msg.at_block {
+            Some(block_id) => extend_block_id(&self.client, block_id).await,
+            None => self.client.get_masterchain_info().await.map(|info| info.last)
+        }.map_err(error::internal)?;
+
+        let balances = futures::stream::iter(addresses)
+            .map(|(address, raw_address)| {
+                let block_id = block_id.clone();
+
+                async move {
+                    let state = self.client.raw_get_account_state_on_block(&raw_address, block_id).await?;
+
+                    anyhow::Ok((address, state.balance.unwrap_or_default()))
+                }
+            })
+            .buffer_unordered(GET_BALANCES_CONCURRENCY)
+            .try_collect()
+            .await
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(GetBalancesResponse { balances, block_id: Some(block_id.into()) })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_account_status(&self, request: Request<GetAccountStatusRequest>) -> Result<Response<GetAccountStatusResponse>, Status> {
+        let client = if no_cache(&request) { self.client.without_cache() } else { self.client.clone() };
+        let msg = trace::request(request.into_inner());
+
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
+
+        let state = client.raw_get_account_state(&address.to_raw_string()).await
+            .map_err(error::internal)?;
+
+        let status = AccountStatus::from(&state);
+        Ok(Response::new(trace::response(GetAccountStatusResponse {
+            account_address: msg.account_address,
+            block_id: Some(state.block_id.into()),
+            status: status.into()
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_jetton_wallet_data(&self, request: Request<GetJettonWalletDataRequest>) -> Result<Response<GetJettonWalletDataResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
+        let contract = TonContract::new(self.client.clone(), MsgAddress { workchain_id: address.chain_id, address: address.bytes });
+
+        let (data, decoded_from_state) = contract.get_wallet_data_from_state(&self.code_hashes.jetton_wallet).await
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(GetJettonWalletDataResponse {
+            account_address: msg.account_address,
+            balance: data.balance.to_string(),
+            owner: data.owner.to_base64_std(),
+            jetton_master: data.master.to_base64_std(),
+            decoded_from_state
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn validate_address(&self, request: Request<ValidateAddressRequest>) -> Result<Response<ValidateAddressResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        Ok(Response::new(trace::response(validate_address(&msg.account_address))))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn pack_address(&self, request: Request<PackAddressRequest>) -> Result<Response<PackAddressResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let response = pack_address(&msg)
+            .map_err(|e| error::invalid_argument("account_address", e))?;
+
+        Ok(Response::new(trace::response(response)))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn unpack_address(&self, request: Request<UnpackAddressRequest>) -> Result<Response<UnpackAddressResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let response = unpack_address(&msg.account_address)
+            .map_err(|e| error::invalid_argument("account_address", e))?;
+
+        Ok(Response::new(trace::response(response)))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_nft_item_data(&self, request: Request<GetNftItemDataRequest>) -> Result<Response<GetNftItemDataResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
+        let contract = TonContract::new(self.client.clone(), MsgAddress { workchain_id: address.chain_id, address: address.bytes });
+
+        let (data, decoded_from_state) = contract.get_nft_data_from_state(&self.code_hashes.nft_item).await
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(GetNftItemDataResponse {
+            account_address: msg.account_address,
+            init: data.init,
+            index: data.index.to_string(),
+            collection_address: data.collection.to_base64_std(),
+            owner_address: data.owner.to_base64_std(),
+            decoded_from_state
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_token_data(&self, request: Request<GetTokenDataRequest>) -> Result<Response<GetTokenDataResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
+        let contract = TonContract::new(self.client.clone(), MsgAddress { workchain_id: address.chain_id, address: address.bytes });
+
+        let (master, wallet, collection, item) = futures::join!(
+            contract.get_jetton_data(),
+            contract.get_wallet_data(),
+            contract.get_collection_data(),
+            contract.get_nft_data()
+        );
+
+        let (contract_type, data) = if let Ok(master) = master {
+            ("jetton_master", Data::JettonMaster(get_token_data_response::JettonMaster {
+                total_supply: master.total_supply.to_string(),
+                mintable: master.mintable,
+                admin_address: master.admin.to_base64_std(),
+                content_uri: content::offchain_uri(&master.content),
+                content: encode_cell(master.content).map_err(error::internal)?
+            }))
+        } else if let Ok(wallet) = wallet {
+            ("jetton_wallet", Data::JettonWallet(get_token_data_response::JettonWallet {
+                balance: wallet.balance.to_string(),
+                owner: wallet.owner.to_base64_std(),
+                jetton_master: wallet.master.to_base64_std()
+            }))
+        } else if let Ok(collection) = collection {
+            ("nft_collection", Data::NftCollection(get_token_data_response::NftCollection {
+                next_item_index: collection.next_item_index.to_string(),
+                owner_address: collection.owner.to_base64_std(),
+                content_uri: content::offchain_uri(&collection.content),
+                content: encode_cell(collection.content).map_err(error::internal)?
+            }))
+        } else if let Ok(item) = item {
+            ("nft_item", Data::NftItem(get_token_data_response::NftItem {
+                init: item.init,
+                index: item.index.to_string(),
+                collection_address: item.collection.to_base64_std(),
+                owner_address: item.owner.to_base64_std()
+            }))
+        } else {
+            // only a getter that exits with an error code tells the contract is not of its kind, an outage must not look like one
+            let errors = [master.err(), wallet.err(), collection.err(), item.err()];
+            if let Some(e) = errors.into_iter().flatten().find(|e| !matches!(e, TonContractError::Contract(_))) {
+                return Err(error::internal(e));
+            }
+
+            return Err(Status::failed_precondition("account is not a jetton or nft contract"));
+        };
+
+        Ok(Response::new(trace::response(GetTokenDataResponse {
+            account_address: msg.account_address,
+            contract_type: contract_type.to_owned(),
+            data: Some(data)
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_seqno(&self, request: Request<GetSeqnoRequest>) -> Result<Response<GetSeqnoResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
+        let contract = TonContract::new(self.client.clone(), MsgAddress { workchain_id: address.chain_id, address: address.bytes });
+
+        let (seqno, decoded_from_state) = contract.seqno_from_state(&self.code_hashes.wallet).await
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(GetSeqnoResponse {
+            account_address: msg.account_address,
+            seqno,
+            decoded_from_state
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_wallet_information(&self, request: Request<GetWalletInformationRequest>) -> Result<Response<GetWalletInformationResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?;
+        let contract = TonContract::new(self.client.clone(), MsgAddress { workchain_id: address.chain_id, address: address.bytes });
+
+        let info = contract.wallet_information().await
+            .map_err(error::internal)?;
+
+        let status = AccountStatus::from(&info.state);
+        Ok(Response::new(trace::response(GetWalletInformationResponse {
+            account_address: msg.account_address,
+            block_id: Some(info.state.block_id.into()),
+            balance: info.state.balance.unwrap_or_default(),
+            wallet_type: info.version.map(|version| version.name().to_owned()),
+            seqno: info.seqno,
+            status: status.into(),
+            last_transaction_id: info.state.last_transaction_id.map(|t| (&address, t).into())
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_elector_stats(&self, _request: Request<GetElectorStatsRequest>) -> Result<Response<GetElectorStatsResponse>, Status> {
+        // both getters run on one block, so the current and past elections are consistent
+        let block_id = self.client.get_masterchain_info().await
+            .map_err(error::internal)?
+            .last;
+        let contract = TonContract::new(self.client.clone(), ELECTOR_ADDRESS).on_block(block_id);
+
+        let (election, past_elections) = try_join!(contract.get_election_data(), contract.get_past_elections())
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(GetElectorStatsResponse {
+            election_id: election.election_id,
+            participants: election.participants.into_iter()
+                .map(|participant| get_elector_stats_response::Participant {
+                    public_key: format!("{:064x}", participant.public_key),
+                    stake: participant.stake.to_string()
+                })
+                .collect(),
+            past_elections: past_elections.into_iter()
+                .map(|election| get_elector_stats_response::PastElection {
+                    election_id: election.election_id,
+                    unfreeze_at: election.unfreeze_at,
+                    stake_held: election.stake_held,
+                    total_stake: election.total_stake.to_string(),
+                    bonuses: election.bonuses.to_string()
+                })
+                .collect()
+        })))
+    }
+}
+
+fn validate_address(input: &str) -> ValidateAddressResponse {
+    let address = match AccountAddressData::from_str(input) {
+        Ok(address) if address.checksum_matches(input) => address,
+        Ok(_) => return ValidateAddressResponse { error: "invalid address checksum".to_owned(), ..Default::default() },
+        Err(e) => return ValidateAddressResponse { error: e.to_string(), ..Default::default() }
+    };
+
+    let given_type = match address.is_bounceable() {
+        None => "raw_form",
+        Some(true) => "friendly_bounceable",
+        Some(false) => "friendly_non_bounceable"
+    };
+    let friendly = |address: AccountAddressData| {
+        let b64url = address.to_flagged_string();
+
+        FriendlyAddress { b64: b64url.replace('-', "+").replace('_', "/"), b64url }
+    };
+    let test_only = address.is_test_only();
+    let (bounceable, non_bounceable) = if test_only {
+        (address.bounceable().test_only(), address.non_bounceable().test_only())
+    } else {
+        (address.bounceable(), address.non_bounceable())
+    };
+
+    ValidateAddressResponse {
+        valid: true,
+        error: String::new(),
+        raw_form: address.to_raw_string(),
+        bounceable: Some(friendly(bounceable)),
+        non_bounceable: Some(friendly(non_bounceable)),
+        given_type: given_type.to_owned(),
+        test_only
     }
 }
 
+/// A shard block only covers the accounts of its shard, reads pinned to a block must name a masterchain one.
+fn check_masterchain(field: &str, block_id: &BlockId) -> Result<(), Status> {
+    if block_id.workchain != MAIN_CHAIN {
+        return Err(error::invalid_argument(field, "block must be a masterchain block"));
+    }
+
+    Ok(())
+}
+
+fn encode_cell(cell: Arc<Cell>) -> Result<String> {
+    let bytes = pack_with(BoC::from_root(cell), BagOfCellsArgs { has_idx: false, has_crc32c: true })
+        .map_err(|e| anyhow::anyhow!("cannot serialize cell: {}", e))?;
+
+    Ok(STANDARD.encode(bytes.as_raw_slice()))
+}
+
+fn pack_address(msg: &PackAddressRequest) -> Result<PackAddressResponse> {
+    let address = address::parse(&msg.account_address)?;
+
+    let address = if msg.bounceable.unwrap_or(true) { address.bounceable() } else { address.non_bounceable() };
+    let address = if msg.test_only { address.test_only() } else { address };
+    let b64url = address.to_flagged_string();
+
+    Ok(PackAddressResponse {
+        address: if msg.url_safe.unwrap_or(true) { b64url } else { b64url.replace('-', "+").replace('_', "/") }
+    })
+}
+
+fn unpack_address(input: &str) -> Result<UnpackAddressResponse> {
+    let address = address::parse(input)?;
+
+    Ok(UnpackAddressResponse {
+        raw_form: address.to_raw_string(),
+        workchain: address.chain_id,
+        hash: hex::encode(address.bytes),
+        bounceable: address.is_bounceable(),
+        test_only: address.is_test_only()
+    })
+}
+
 impl AccountService {
-    async fn fetch_account_state(&self, msg: &GetAccountStateRequest) -> Result<RawFullAccountState> {
+    async fn call_get_method(&self, msg: RunGetMethodRequest, block_id: Option<TonBlockIdExt>) -> Result<RunGetMethodResponse, Status> {
+        let address = self.workchains.parse_address("account_address", &msg.account_address)?.to_raw_string();
+        let stack = msg.stack.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| error::invalid_argument("stack", e))?;
+
+        let method_id = match msg.method {
+            Some(run_get_method_request::Method::MethodName(name)) => method_id::compute(&name),
+            Some(run_get_method_request::Method::MethodId(id)) => id,
+            None => return Err(error::invalid_argument("method", "method is required"))
+        };
+        let method_id = method_id::validate(method_id)
+            .map_err(|e| error::invalid_argument("method", e))?;
+
+        let result = match block_id {
+            Some(block_id) => self.client.run_get_method_on_block(address, method_id, stack, block_id).await,
+            None => self.client.run_get_method(address, method_id, stack).await
+        }.map_err(error::internal)?;
+
+        // a failed get-method leaves whatever it had on the stack, the exit code tells what went wrong
+        let decoded = if matches!(result.exit_code, 0 | 1) {
+            abi::decode(&msg.abi, &result.stack)
+                .map_err(|e| error::invalid_argument("abi", e))?
+        } else {
+            Vec::new()
+        };
+        let stack = result.stack.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<_>>>()
+            .map_err(error::internal)?;
+
+        Ok(RunGetMethodResponse {
+            exit_code: result.exit_code,
+            gas_used: result.gas_used,
+            stack,
+            decoded
+        })
+    }
+
+    async fn fetch_account_state(&self, address: &str, msg: &GetAccountStateRequest, no_cache: bool) -> Result<RawFullAccountState> {
         let state = match &msg.criteria {
             None => {
-                let block_id = self.client.get_masterchain_info().await?.last;
+                let client = if no_cache { self.client.without_cache() } else { self.client.clone() };
 
-                self.client.raw_get_account_state_at_least_block(&msg.account_address, &block_id).await?
+                client.raw_get_account_state_at_least_last_block(address).await?
             },
             Some(get_account_state_request::Criteria::BlockId(block_id)) => {
                 let block_id = extend_block_id(&self.client, block_id).await?;
 
-                self.client.raw_get_account_state_on_block(&msg.account_address, block_id).await?
+                self.client.raw_get_account_state_on_block(address, block_id).await?
             },
             Some(get_account_state_request::Criteria::AtLeastBlockId(block_id)) => {
                 let block_id = extend_block_id(&self.client, block_id).await?;
 
-                self.client.raw_get_account_state_at_least_block(&msg.account_address, &block_id).await?
+                self.client.raw_get_account_state_at_least_block(address, &block_id).await?
             },
             Some(get_account_state_request::Criteria::TransactionId(tx_id)) => {
-                self.client.raw_get_account_state_by_transaction(&msg.account_address, tx_id.clone().into()).await?
+                self.client.raw_get_account_state_by_transaction(address, tx_id.clone().into()).await?
+            },
+            Some(get_account_state_request::Criteria::MasterchainSeqno(seqno)) => {
+                let block_id = self.client.look_up_block_by_seqno(MAIN_CHAIN, MAIN_SHARD, *seqno).await?;
+
+                self.client.raw_get_account_state_on_block(address, block_id).await?
             },
         };
         Ok(state)
     }
 
-    async fn fetch_shard_account_cell(&self, msg: &GetShardAccountCellRequest) -> Result<(TonBlockIdExt, TvmCell)> {
+    async fn fetch_shard_account_cell(&self, address: &str, msg: &GetShardAccountCellRequest) -> Result<(TonBlockIdExt, TvmCell)> {
         let (block_id, cell) = match &msg.criteria {
             None => {
                 let block_id = self.client.get_masterchain_info().await?.last;
-                let cell = self.client.get_shard_account_cell_at_least_block(&msg.account_address, &block_id).await?;
+                let cell = self.client.get_shard_account_cell_at_least_block(address, &block_id).await?;
 
                 (block_id, cell)
             }
             Some(get_shard_account_cell_request::Criteria::BlockId(block_id)) => {
                 let block_id = extend_block_id(&self.client, block_id).await?;
-                let cell = self.client.get_shard_account_cell_on_block(&msg.account_address, block_id.clone()).await?;
+                let cell = self.client.get_shard_account_cell_on_block(address, block_id.clone()).await?;
 
                 (block_id, cell)
             },
             Some(get_shard_account_cell_request::Criteria::TransactionId(tx_id)) => {
-                let state = self.client.raw_get_account_state_by_transaction(&msg.account_address, tx_id.clone().into()).await?;
-                let cell = self.client.get_shard_account_cell_on_block(&msg.account_address, state.block_id.clone()).await?;
+                let state = self.client.raw_get_account_state_by_transaction(address, tx_id.clone().into()).await?;
+                let cell = self.client.get_shard_account_cell_on_block(address, state.block_id.clone()).await?;
 
                 (state.block_id, cell)
             },
             Some(get_shard_account_cell_request::Criteria::AtLeastBlockId(block_id)) => {
                 let block_id = extend_block_id(&self.client, block_id).await?;
-                let state = self.client.raw_get_account_state_at_least_block(&msg.account_address, &block_id).await?;
-                let cell = self.client.get_shard_account_cell_on_block(&msg.account_address, state.block_id.clone()).await?;
+                let state = self.client.raw_get_account_state_at_least_block(address, &block_id).await?;
+                let cell = self.client.get_shard_account_cell_on_block(address, state.block_id.clone()).await?;
 
                 (state.block_id, cell)
             }
@@ -171,11 +655,68 @@ mod tests {
     use tonic::Request;
     use tonlibjson_client::ton::TonClientBuilder;
     use tracing_test::traced_test;
-    use crate::account::AccountService;
+    use crate::account::{check_masterchain, AccountService, pack_address, unpack_address, validate_address};
+    use crate::drain::Drain;
     use crate::ton::account_service_server::AccountService as BaseAccountService;
-    use crate::ton::{get_account_transactions_request, GetAccountStateRequest, GetAccountTransactionsRequest, GetShardAccountCellRequest, PartialTransactionId};
+    use crate::ton::{get_account_transactions_request, run_get_method_batch_response, run_get_method_request, BlockId, GetAccountStateRequest, GetAccountTransactionsRequest, GetShardAccountCellRequest, PackAddressRequest, PartialTransactionId, RunGetMethodBatchRequest, RunGetMethodRequest};
     use crate::ton::get_account_transactions_request::bound;
 
+    #[test]
+    fn validate_address_returns_all_forms() {
+        let bounceable = validate_address("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zj");
+        assert!(bounceable.valid);
+        assert_eq!(bounceable.given_type, "friendly_bounceable");
+        assert_eq!(bounceable.bounceable.as_ref().unwrap().b64url, "EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zj");
+        assert_eq!(bounceable.bounceable.as_ref().unwrap().b64, "EQCkgtq1pKJh4Zpif/z4RR2aYmespuImTw15amEacGX+k6Zj");
+
+        let raw = validate_address(&bounceable.raw_form);
+        assert!(raw.valid);
+        assert_eq!(raw.given_type, "raw_form");
+        assert_eq!(raw.bounceable, bounceable.bounceable);
+
+        let non_bounceable = validate_address(&raw.non_bounceable.as_ref().unwrap().b64url);
+        assert_eq!(non_bounceable.given_type, "friendly_non_bounceable");
+        assert_eq!(non_bounceable.raw_form, bounceable.raw_form);
+    }
+
+    #[test]
+    fn pack_address_round_trips() {
+        let raw = unpack_address("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zj").unwrap();
+        assert_eq!(raw.bounceable, Some(true));
+        assert!(!raw.test_only);
+
+        let request = PackAddressRequest { account_address: raw.raw_form.clone(), bounceable: None, test_only: false, url_safe: None };
+        assert_eq!(pack_address(&request).unwrap().address, "EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zj");
+
+        let request = PackAddressRequest { url_safe: Some(false), ..request };
+        assert_eq!(pack_address(&request).unwrap().address, "EQCkgtq1pKJh4Zpif/z4RR2aYmespuImTw15amEacGX+k6Zj");
+
+        let unpacked = unpack_address(&raw.raw_form).unwrap();
+        assert_eq!(unpacked.bounceable, None);
+        assert_eq!(unpacked.hash, raw.hash);
+    }
+
+    #[test]
+    fn unpack_address_rejects_bad_checksum() {
+        assert!(unpack_address("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zk").is_err());
+    }
+
+    #[test]
+    fn check_masterchain_rejects_shard_blocks() {
+        let block_id = |workchain| BlockId { workchain, shard: i64::MIN, seqno: 1, root_hash: None, file_hash: None };
+
+        assert!(check_masterchain("at_block", &block_id(-1)).is_ok());
+        assert_eq!(check_masterchain("at_block", &block_id(0)).unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn validate_address_rejects_bad_checksum() {
+        let response = validate_address("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zk");
+
+        assert!(!response.valid);
+        assert_eq!(response.error, "invalid address checksum");
+    }
+
     #[tokio::test]
     #[traced_test]
     #[ignore]
@@ -184,7 +725,7 @@ mod tests {
         let mut client = TonClientBuilder::default().await.unwrap();
         client.ready().await.unwrap();
         tracing::info!("ready");
-        let svc = AccountService::new(client);
+        let svc = AccountService::new(client, Drain::default(), Default::default(), Default::default());
         let req = Request::new(GetAccountTransactionsRequest {
             account_address: "EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zj".to_string(),
             order: 1,
@@ -213,7 +754,7 @@ mod tests {
         let mut client = TonClientBuilder::default().await.unwrap();
         client.ready().await.unwrap();
         tracing::info!("ready");
-        let svc = AccountService::new(client);
+        let svc = AccountService::new(client, Drain::default(), Default::default(), Default::default());
         let req = Request::new(GetAccountStateRequest {
             account_address: "EQCaatdRleXHdMCc3ONQsZklcF32jyCiJhHyN3YEKxPXMhsF".to_string(),
             criteria: None
@@ -233,7 +774,7 @@ mod tests {
         let mut client = TonClientBuilder::default().await.unwrap();
         client.ready().await.unwrap();
         tracing::info!("ready");
-        let svc = AccountService::new(client);
+        let svc = AccountService::new(client, Drain::default(), Default::default(), Default::default());
         let req = Request::new(GetShardAccountCellRequest {
             account_address: "EQCaatdRleXHdMCc3ONQsZklcF32jyCiJhHyN3YEKxPXMhsF".to_string(),
             criteria: None
@@ -244,4 +785,39 @@ mod tests {
         tracing::info!(resp = ?resp);
         assert!(resp.is_ok())
     }
+
+    #[tokio::test]
+    #[traced_test]
+    #[ignore]
+    async fn run_get_method_batch_keeps_call_order() {
+        let mut client = TonClientBuilder::default().await.unwrap();
+        client.ready().await.unwrap();
+        let svc = AccountService::new(client, Drain::default(), Default::default(), Default::default());
+        let call = |account_address: &str| RunGetMethodRequest {
+            account_address: account_address.to_owned(),
+            method: Some(run_get_method_request::Method::MethodName("seqno".to_owned())),
+            stack: vec![],
+            at_block: None,
+            abi: vec![]
+        };
+
+        let rejected = svc.run_get_method_batch(Request::new(RunGetMethodBatchRequest {
+            calls: vec![RunGetMethodRequest { at_block: Some(BlockId { workchain: -1, shard: i64::MIN, seqno: 1, root_hash: None, file_hash: None }), ..call("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zj") }],
+            at_block: None
+        })).await.unwrap_err();
+        assert_eq!(rejected.code(), tonic::Code::InvalidArgument);
+
+        let resp = svc.run_get_method_batch(Request::new(RunGetMethodBatchRequest {
+            calls: vec![call("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zj"), call("not an address")],
+            at_block: None
+        })).await.unwrap().into_inner();
+
+        assert!(resp.block_id.is_some());
+        assert_eq!(resp.results.len(), 2);
+        assert!(matches!(resp.results[0].result, Some(run_get_method_batch_response::item::Result::Response(_))));
+        let Some(run_get_method_batch_response::item::Result::Error(ref error)) = resp.results[1].result else {
+            panic!("expected the invalid address to fail its call");
+        };
+        assert_eq!(error.code, tonic::Code::InvalidArgument as i32);
+    }
 }
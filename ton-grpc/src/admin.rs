@@ -0,0 +1,91 @@
+use derive_new::new;
+use tonic::{async_trait, Request, Response, Status};
+use tonlibjson_client::ton::TonClient;
+use crate::trace;
+use crate::ton::admin_service_server::AdminService as BaseAdminService;
+use crate::ton::{CacheStats, FlushCacheRequest, FlushCacheResponse, GetCacheStatsRequest, GetCacheStatsResponse};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(new)]
+pub struct AdminService {
+    client: TonClient
+}
+
+#[async_trait]
+impl BaseAdminService for AdminService {
+    #[tracing::instrument(skip_all, err)]
+    async fn get_cache_stats(&self, _request: Request<GetCacheStatsRequest>) -> Result<Response<GetCacheStatsResponse>, Status> {
+        let caches = self.client.cache_stats().into_iter()
+            .map(|stats| {
+                let requests = stats.hits + stats.misses;
+
+                CacheStats {
+                    name: stats.name.to_owned(),
+                    size: stats.size as u64,
+                    hits: stats.hits,
+                    misses: stats.misses,
+                    hit_rate: if requests == 0 { 0. } else { stats.hits as f64 / requests as f64 }
+                }
+            })
+            .collect();
+
+        Ok(Response::new(trace::response(GetCacheStatsResponse { caches })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn flush_cache(&self, request: Request<FlushCacheRequest>) -> Result<Response<FlushCacheResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        if !self.client.flush_cache(&msg.name) {
+            return Err(Status::not_found(format!("cache {} is not enabled", msg.name)));
+        }
+
+        Ok(Response::new(trace::response(FlushCacheResponse {})))
+    }
+}
+
+pub fn check_api_key(api_key: String) -> impl Fn(Request<()>) -> Result<Request<()>, Status> + Clone {
+    move |request: Request<()>| {
+        let given = request.metadata().get(API_KEY_HEADER).map(|value| value.as_bytes());
+
+        match given {
+            Some(given) if constant_time_eq(given, api_key.as_bytes()) => Ok(request),
+            _ => Err(Status::unauthenticated(format!("valid {} is required", API_KEY_HEADER)))
+        }
+    }
+}
+
+/// Compares without short-circuiting, so the time taken doesn't tell how much of the key matched.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::{Code, Request};
+    use crate::admin::{check_api_key, constant_time_eq, API_KEY_HEADER};
+
+    #[test]
+    fn check_api_key_rejects_missing_and_wrong_keys() {
+        let check = check_api_key("secret".to_owned());
+
+        let mut request = Request::new(());
+        request.metadata_mut().insert(API_KEY_HEADER, "secret".parse().unwrap());
+        assert!(check(request).is_ok());
+
+        let mut request = Request::new(());
+        request.metadata_mut().insert(API_KEY_HEADER, "other".parse().unwrap());
+        assert_eq!(check(request).unwrap_err().code(), Code::Unauthenticated);
+
+        assert_eq!(check(Request::new(())).unwrap_err().code(), Code::Unauthenticated);
+    }
+
+    #[test]
+    fn constant_time_eq_requires_same_length() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+}
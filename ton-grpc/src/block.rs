@@ -1,19 +1,37 @@
 #![allow(clippy::blocks_in_conditions)]
 
-use anyhow::Context;
+use std::pin::Pin;
+use std::time::Duration;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use futures::stream::BoxStream;
-use futures::{StreamExt, TryStreamExt};
+use futures::{StreamExt, try_join, TryStreamExt};
 use tonic::{async_trait, Request, Response, Status};
 use derive_new::new;
-use tonlibjson_client::ton::TonClient;
-use crate::helpers::extend_block_id;
+use tonlibjson_client::block::{BlocksBlockLinkBack, BlocksShardBlockProof, TonBlockIdExt};
+use tonlibjson_client::ton::{BlockProof, TonClient, TooManyKeyBlocks};
+use crate::cursor::BlockTxCursor;
+use crate::drain::Drain;
+use crate::error;
+use crate::trace;
+use crate::helpers::{config, extend_block_id, MAIN_CHAIN, MAIN_SHARD};
+use crate::helpers::workchain::AllowedWorkchains;
 use crate::ton::block_service_server::BlockService as BaseBlockService;
-use crate::ton::{AccountAddress, BlockId, BlockIdExt, GetTransactionIdsRequest, GetLastBlockRequest, GetShardsResponse, TransactionId, GetTransactionsRequest, Transaction};
+use crate::ton::{AccountAddress, BlockId, BlockIdExt, GetTransactionIdsRequest, GetLastBlockRequest, GetShardsResponse, TransactionId, GetTransactionsRequest, Transaction, GetMasterchainInfoRequest, MasterchainInfo, GetSyncStatusRequest, SyncStatus, LookupBlockRequest, BlockHeader, GetBlockHeaderRequest, GetBlockProofRequest, GetBlockProofResponse, GetConfigParamRequest, GetConfigParamResponse, GetShardBlockProofRequest, GetShardBlockProofResponse, SubscribeMasterchainBlocksRequest, SubscribeMasterchainBlocksResponse};
+use crate::ton::get_block_proof_response::{BlockLink, BlockSignatures};
+use crate::ton::get_block_proof_response::block_signatures::Signature;
+use crate::ton::get_shard_block_proof_response::ShardBlockLink;
 use crate::ton::get_transaction_ids_request::Order;
+use crate::ton::lookup_block_request::Criteria;
+
+/// Masterchain blocks a subscription may replay before going live.
+const SUBSCRIPTION_MAX_BACKFILL: i32 = 1000;
 
 #[derive(new)]
 pub struct BlockService {
-    client: TonClient
+    client: TonClient,
+    drain: Drain,
+    workchains: AllowedWorkchains,
+    subscription_poll_interval: Duration
 }
 
 #[async_trait]
@@ -21,100 +39,500 @@ impl BaseBlockService for BlockService {
     #[tracing::instrument(skip_all, err)]
     async fn get_last_block(&self, _request: Request<GetLastBlockRequest>) -> Result<Response<BlockIdExt>, Status> {
         let block = self.client.get_masterchain_info().await
-            .map_err(|e: anyhow::Error| Status::internal(e.to_string()))?.last;
+            .map_err(error::internal)?.last;
+
+        Ok(Response::new(trace::response(block.into())))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_masterchain_info(&self, _request: Request<GetMasterchainInfoRequest>) -> Result<Response<MasterchainInfo>, Status> {
+        let (info, global_id) = try_join!(
+            self.client.get_masterchain_info(),
+            self.client.get_global_id()
+        ).map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(MasterchainInfo {
+            last: Some(info.last.into()),
+            init: Some(info.init.into()),
+            state_root_hash: info.state_root_hash,
+            global_id
+        })))
+    }
 
-        Ok(Response::new(block.into()))
+    #[tracing::instrument(skip_all, err)]
+    async fn get_sync_status(&self, _request: Request<GetSyncStatusRequest>) -> Result<Response<SyncStatus>, Status> {
+        let status = self.client.get_sync_status().await
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(SyncStatus {
+            synced: status.synced,
+            current_seqno: status.current_seqno,
+            target_seqno: status.target_seqno,
+            lag_seconds: status.lag.map(|lag| lag.as_secs() as i64)
+        })))
     }
 
     #[tracing::instrument(skip_all, err)]
     async fn get_block(&self, request: Request<BlockId>) -> Result<Response<BlockIdExt>, Status> {
-        let block_id = extend_block_id(&self.client, &request.into_inner()).await
-            .map_err(|e: anyhow::Error| Status::internal(e.to_string()))?;
+        let msg = trace::request(request.into_inner());
+        self.workchains.check(msg.workchain)?;
+
+        let block_id = extend_block_id(&self.client, &msg).await
+            .map_err(error::internal)?;
 
-        Ok(Response::new(block_id.into()))
+        Ok(Response::new(trace::response(block_id.into())))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn lookup_block(&self, request: Request<LookupBlockRequest>) -> Result<Response<BlockIdExt>, Status> {
+        let msg = trace::request(request.into_inner());
+        self.workchains.check(msg.workchain)?;
+
+        let block_id = match msg.criteria {
+            Some(Criteria::Seqno(seqno)) => self.client.look_up_block_by_seqno(msg.workchain, msg.shard, seqno).await,
+            Some(Criteria::Lt(lt)) => self.client.look_up_block_by_lt(msg.workchain, msg.shard, lt).await,
+            Some(Criteria::Utime(utime)) if utime <= 0 => return Err(error::invalid_argument("utime", "utime must be positive")),
+            Some(Criteria::Utime(utime)) => self.client.look_up_block_by_utime(msg.workchain, msg.shard, utime).await,
+            None => return Err(error::invalid_argument("criteria", "criteria is required"))
+        }.map_err(error::not_found_or_internal)?;
+
+        Ok(Response::new(trace::response(block_id.into())))
     }
 
     #[tracing::instrument(skip_all, err)]
     async fn get_shards(&self, request: Request<BlockId>) -> Result<Response<GetShardsResponse>, Status> {
-        let block_id = extend_block_id(&self.client, &request.into_inner()).await
-            .map_err(|e: anyhow::Error| Status::internal(e.to_string()))?;
+        let msg = trace::request(request.into_inner());
+        self.workchains.check(msg.workchain)?;
+
+        let block_id = extend_block_id(&self.client, &msg).await
+            .map_err(error::internal)?;
 
         let shards = self.client.get_shards_by_block_id(block_id).await
-            .map_err(|e: anyhow::Error| Status::internal(e.to_string()))?;
+            .map_err(error::internal)?;
 
-        Ok(Response::new(GetShardsResponse {
+        Ok(Response::new(trace::response(GetShardsResponse {
             shards: shards.into_iter().map(|i| i.into()).collect()
-        }))
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_block_proof(&self, request: Request<GetBlockProofRequest>) -> Result<Response<GetBlockProofResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let BlockProof { proof, signatures } = self.client.get_block_proof(msg.from_seqno, msg.to_seqno).await
+            .map_err(|e| {
+                if let Some(e) = e.downcast_ref::<TooManyKeyBlocks>() {
+                    return error::invalid_argument("to_seqno", e);
+                }
+
+                error::internal(e)
+            })?;
+
+        let (from, to, complete) = proof_ends(&proof, msg.from_seqno < msg.to_seqno);
+        let steps = block_links(proof.mc_proof)
+            .map_err(error::internal)?;
+        let signatures = signatures.into_iter()
+            .map(|block| Ok(BlockSignatures {
+                id: Some(block.id.into()),
+                signatures: block.signatures.into_iter()
+                    .map(|signature| Ok(Signature {
+                        node_id_short: STANDARD.decode(signature.node_id_short)?,
+                        signature: STANDARD.decode(signature.signature)?
+                    }))
+                    .collect::<Result<_, base64::DecodeError>>()?
+            }))
+            .collect::<Result<_, base64::DecodeError>>()
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(GetBlockProofResponse {
+            from: Some(from.into()),
+            to: Some(to.into()),
+            steps,
+            complete,
+            signatures
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_shard_block_proof(&self, request: Request<GetShardBlockProofRequest>) -> Result<Response<GetShardBlockProofResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+        let Some(block_id) = msg.block_id else {
+            return Err(error::invalid_argument("block_id", "block_id is required"));
+        };
+        self.workchains.check(block_id.workchain)?;
+
+        let (block_id, from) = try_join!(
+            extend_block_id(&self.client, &block_id),
+            async {
+                match msg.from_seqno {
+                    Some(seqno) => self.client.look_up_block_by_seqno(MAIN_CHAIN, MAIN_SHARD, seqno).await.map(Some),
+                    None => Ok(None)
+                }
+            }
+        ).map_err(error::internal)?;
+
+        let proof = self.client.get_shard_block_proof(block_id, from).await
+            .map_err(error::internal)?;
+
+        let links = proof.links.into_iter()
+            .map(|link| Ok(ShardBlockLink { id: Some(link.id.into()), proof: STANDARD.decode(link.proof)? }))
+            .collect::<Result<_, base64::DecodeError>>()
+            .map_err(error::internal)?;
+        let mc_proof = block_links(proof.mc_proof)
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(GetShardBlockProofResponse {
+            from: Some(proof.from.into()),
+            mc_id: Some(proof.mc_id.into()),
+            links,
+            mc_proof
+        })))
+    }
+
+    type SubscribeMasterchainBlocksStream = BoxStream<'static, Result<SubscribeMasterchainBlocksResponse, Status>>;
+
+    #[tracing::instrument(skip_all, err)]
+    async fn subscribe_masterchain_blocks(&self, request: Request<SubscribeMasterchainBlocksRequest>) -> Result<Response<Self::SubscribeMasterchainBlocksStream>, Status> {
+        let permit = self.drain.acquire(request.remote_addr())?;
+        let msg = trace::request(request.into_inner());
+        if let Some(from_seqno) = msg.from_seqno {
+            if from_seqno <= 0 {
+                return Err(error::invalid_argument("from_seqno", "from_seqno must be positive"));
+            }
+
+            let last = self.client.get_masterchain_info().await
+                .map_err(error::internal)?.last;
+            if last.seqno - from_seqno > SUBSCRIPTION_MAX_BACKFILL {
+                return Err(error::invalid_argument("from_seqno", format!("backfill is limited to {} blocks", SUBSCRIPTION_MAX_BACKFILL)));
+            }
+        }
+
+        let include_shards = msg.include_shards;
+        let client = self.client.clone();
+        let workchains = self.workchains.clone();
+        let stream = self.client.masterchain_block_stream(msg.from_seqno, self.subscription_poll_interval)
+            .and_then(move |block| {
+                let client = client.clone();
+                let workchains = workchains.clone();
+
+                async move {
+                    let shards = if include_shards {
+                        client.get_shards_by_block_id(block.clone()).await?
+                            .into_iter()
+                            .filter(|shard| workchains.check(shard.workchain).is_ok())
+                            .map(Into::into)
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+
+                    Ok(trace::response(SubscribeMasterchainBlocksResponse { masterchain: Some(block.into()), shards }))
+                }
+            })
+            .map_err(error::internal)
+            .boxed();
+
+        Ok(Response::new(self.drain.wrap_subscription(permit, stream)))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_config_param(&self, request: Request<GetConfigParamRequest>) -> Result<Response<GetConfigParamResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let block_id = match msg.seqno {
+            Some(seqno) => self.client.look_up_block_by_seqno(MAIN_CHAIN, MAIN_SHARD, seqno).await
+                .map_err(error::not_found_or_internal)?,
+            None => self.client.get_masterchain_info().await
+                .map_err(error::internal)?
+                .last
+        };
+
+        let cell = self.client.get_config_param_on_block(block_id.clone(), msg.config_id).await
+            .map_err(error::internal)?;
+        if cell.bytes.is_empty() {
+            return Ok(Response::new(trace::response(GetConfigParamResponse { block_id: Some(block_id.into()), found: false, cell: None, decoded: None })));
+        }
+
+        let decoded = config::decode(msg.config_id, &cell.bytes)
+            .unwrap_or_else(|e| {
+                tracing::warn!(config_id = msg.config_id, error = ?e, "cannot decode config param");
+
+                None
+            });
+
+        Ok(Response::new(trace::response(GetConfigParamResponse {
+            block_id: Some(block_id.into()),
+            found: true,
+            cell: Some(cell.bytes),
+            decoded
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn get_block_header(&self, request: Request<GetBlockHeaderRequest>) -> Result<Response<BlockHeader>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let block_id = msg.block_id
+            .ok_or_else(|| error::invalid_argument("block_id", "block_id is required"))?;
+        self.workchains.check(block_id.workchain)?;
+        let block_id = extend_block_id(&self.client, &block_id).await
+            .map_err(error::internal)?;
+
+        let header: BlockHeader = self.client.get_block_header_by_block_id(block_id).await
+            .map_err(error::internal)?
+            .into();
+
+        let header = match msg.fields {
+            Some(mask) if !mask.paths.is_empty() => header.masked(&mask.paths),
+            _ => header
+        };
+
+        Ok(Response::new(trace::response(header)))
     }
 
     type GetTransactionIdsStream = BoxStream<'static, Result<TransactionId, Status>>;
 
     #[tracing::instrument(skip_all, err)]
     async fn get_transaction_ids(&self, request: Request<GetTransactionIdsRequest>) -> Result<Response<Self::GetTransactionIdsStream>, Status> {
-        let msg = request.into_inner();
+        let permit = self.drain.acquire(request.remote_addr())?;
+        let msg = trace::request(request.into_inner());
 
+        check_limit(msg.limit)?;
         let order = msg.order();
-        let block_id = msg.block_id.context("block id is required")
-            .map_err(|e| Status::internal(e.to_string()))?;
+        let cursor = msg.cursor.as_deref()
+            .map(BlockTxCursor::decode)
+            .transpose()
+            .map_err(|e| error::invalid_argument("cursor", e))?;
+
+        let (block_id, after) = match cursor {
+            Some(cursor) => {
+                if order == Order::Unordered || cursor.reverse != (order == Order::Desc) {
+                    return Err(error::invalid_argument("cursor", "cursor does not match the requested order"));
+                }
+
+                (cursor.block_id, Some(cursor.after))
+            },
+            None => {
+                let block_id = msg.block_id
+                    .ok_or_else(|| error::invalid_argument("block_id", "block_id is required"))?;
+                self.workchains.check(block_id.workchain)?;
+                let block_id = extend_block_id(&self.client, &block_id).await
+                    .map_err(error::internal)?;
 
+                (block_id, None)
+            }
+        };
         let chain_id = block_id.workchain;
-        let block_id = extend_block_id(&self.client, &block_id).await
-            .map_err(|e| Status::internal(e.to_string()))?;
 
-        let stream = match order {
-            Order::Unordered => self.client.get_block_tx_stream_unordered(&block_id).boxed(),
-            Order::Asc => self.client.get_block_tx_id_stream(&block_id, false).boxed(),
-            Order::Desc => self.client.get_block_tx_id_stream(&block_id, true).boxed(),
+        let stream: BoxStream<'static, anyhow::Result<TransactionId>> = match order {
+            Order::Unordered => self.client.get_block_tx_stream_unordered(&block_id)
+                .map_ok(move |t| { (chain_id, t).into() })
+                .boxed(),
+            Order::Asc | Order::Desc => {
+                let reverse = order == Order::Desc;
+                let cursor_block_id = block_id.clone();
+
+                self.client.get_block_tx_id_stream_from(&block_id, after, reverse)
+                    .and_then(move |t| {
+                        let cursor = BlockTxCursor::new(cursor_block_id.clone(), reverse, (&t).into()).encode();
+
+                        async move {
+                            Ok(TransactionId { cursor: Some(cursor?), ..(chain_id, t).into() })
+                        }
+                    })
+                    .boxed()
+            }
         };
 
-        let stream = stream
-            .map_ok(move |t| { (chain_id, t).into() })
-            .map_err(|e| Status::internal(e.to_string()))
+        let stream = limit(stream, msg.limit, |t: &mut TransactionId| t.has_more = true)
+            .map_err(error::internal)
             .boxed();
 
-        Ok(Response::new(stream))
+        Ok(Response::new(self.drain.wrap(permit, stream.map_ok(trace::response).boxed())))
     }
 
     type GetAccountAddressesStream = BoxStream<'static, Result<AccountAddress, Status>>;
 
     #[tracing::instrument(skip_all, err)]
     async fn get_account_addresses(&self, request: Request<BlockId>) -> Result<Response<Self::GetAccountAddressesStream>, Status> {
-        let msg = request.into_inner();
+        let permit = self.drain.acquire(request.remote_addr())?;
+        let msg = trace::request(request.into_inner());
+        self.workchains.check(msg.workchain)?;
         let block_id = extend_block_id(&self.client, &msg).await
-            .map_err(|e: anyhow::Error| Status::internal(e.to_string()))?;
+            .map_err(error::internal)?;
 
         let stream = self.client.get_accounts_in_block_stream(&block_id)
             .map_ok(|a| AccountAddress { address: a.to_string() })
-            .map_err(|e| Status::internal(e.to_string()))
+            .map_err(error::internal)
             .boxed();
 
-        Ok(Response::new(stream))
+        Ok(Response::new(self.drain.wrap(permit, stream.map_ok(trace::response).boxed())))
     }
 
     type GetTransactionsStream = BoxStream<'static, Result<Transaction, Status>>;
 
     async fn get_transactions(&self, request: Request<GetTransactionsRequest>) -> Result<Response<Self::GetTransactionsStream>, Status> {
-        let msg = request.into_inner();
+        let permit = self.drain.acquire(request.remote_addr())?;
+        let msg = trace::request(request.into_inner());
 
+        check_limit(msg.limit)?;
         // TODO[akostylev0]
         let _order = msg.order();
-        let block_id = msg.block_id.context("block id is required")
-            .map_err(|e| Status::internal(e.to_string()))?;
+        let cursor = msg.cursor.as_deref()
+            .map(BlockTxCursor::decode)
+            .transpose()
+            .map_err(|e| error::invalid_argument("cursor", e))?;
+
+        let (block_id, after) = match cursor {
+            Some(cursor) => {
+                if cursor.reverse {
+                    return Err(error::invalid_argument("cursor", "cursor does not match the requested order"));
+                }
+
+                (cursor.block_id, Some(cursor.after))
+            },
+            None => {
+                let block_id = msg.block_id
+                    .ok_or_else(|| error::invalid_argument("block_id", "block_id is required"))?;
+                self.workchains.check(block_id.workchain)?;
+                let block_id = extend_block_id(&self.client, &block_id).await
+                    .map_err(error::internal)?;
 
+                (block_id, None)
+            }
+        };
         let chain_id = block_id.workchain;
-        let block_id = extend_block_id(&self.client, &block_id).await
-            .map_err(|e| Status::internal(e.to_string()))?;
+        let cursor_block_id = block_id.clone();
 
-        let stream = self.client.get_block_tx_stream(&block_id, false).boxed();
+        let stream = self.client.get_block_tx_stream_from(&block_id, after, false).boxed();
 
         let stream = stream
-            .map(move |tx| match tx {
-                Ok(tx) => (chain_id, tx).try_into(),
+            .map(move |tx| -> anyhow::Result<Transaction> { match tx {
+                Ok(tx) => {
+                    let cursor = BlockTxCursor::new(cursor_block_id.clone(), false, (&tx).try_into()?).encode()?;
+                    let mut tx: Transaction = (chain_id, tx).try_into()?;
+                    if let Some(id) = tx.id.as_mut() {
+                        id.cursor = Some(cursor);
+                    }
+
+                    Ok(tx)
+                },
                 Err(e) => Err(e)
+            }})
+            .boxed();
+        let stream = limit(stream, msg.limit, |tx: &mut Transaction| {
+                if let Some(id) = tx.id.as_mut() {
+                    id.has_more = true;
+                }
             })
-            .map_err(|e| Status::internal(e.to_string()))
+            .map_err(error::internal)
             .boxed();
 
-        Ok(Response::new(stream))
+        Ok(Response::new(self.drain.wrap(permit, stream.map_ok(trace::response).boxed())))
+    }
+}
+
+fn check_limit(limit: Option<u32>) -> Result<(), Status> {
+    if limit == Some(0) {
+        return Err(error::invalid_argument("limit", "limit must be positive"));
+    }
+
+    Ok(())
+}
+
+/// Ends `stream` after `limit` items and applies `has_more` to the last one when the stream goes on.
+fn limit<T: Send + 'static>(
+    stream: BoxStream<'static, anyhow::Result<T>>,
+    limit: Option<u32>,
+    has_more: impl Fn(&mut T) + Send + 'static
+) -> BoxStream<'static, anyhow::Result<T>> {
+    let Some(limit) = limit else {
+        return stream;
+    };
+    let limit = limit as usize;
+
+    futures::stream::unfold((stream.take(limit + 1).peekable(), 0, has_more), move |(mut stream, taken, has_more)| async move {
+        if taken == limit {
+            return None;
+        }
+
+        let mut item = stream.next().await?;
+        if taken + 1 == limit && Pin::new(&mut stream).peek().await.is_some() {
+            if let Ok(item) = item.as_mut() {
+                has_more(item);
+            }
+        }
+
+        Some((item, (stream, taken + 1, has_more)))
+    }).boxed()
+}
+
+/// The requested ends of `proof` and whether its links reach from one to the other.
+/// The links always lead from the newer block back to the older one, so a `forward` proof starts at `mc_id`.
+fn proof_ends(proof: &BlocksShardBlockProof, forward: bool) -> (TonBlockIdExt, TonBlockIdExt, bool) {
+    let complete = match proof.mc_proof.last() {
+        Some(link) => link.to == proof.mc_id,
+        None => proof.from == proof.mc_id
+    };
+
+    if forward {
+        (proof.mc_id.clone(), proof.from.clone(), complete)
+    } else {
+        (proof.from.clone(), proof.mc_id.clone(), complete)
+    }
+}
+
+fn block_links(links: Vec<BlocksBlockLinkBack>) -> Result<Vec<BlockLink>, base64::DecodeError> {
+    links.into_iter()
+        .map(|link| Ok(BlockLink {
+            to_key_block: link.to_key_block,
+            from: Some(link.from.into()),
+            to: Some(link.to.into()),
+            dest_proof: STANDARD.decode(link.dest_proof)?,
+            proof: STANDARD.decode(link.proof)?,
+            state_proof: STANDARD.decode(link.state_proof)?
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{stream, StreamExt, TryStreamExt};
+    use tonlibjson_client::block::{BlocksShardBlockProof, TonBlockIdExt};
+    use crate::block::{limit, proof_ends};
+
+    fn block(seqno: i32) -> TonBlockIdExt {
+        TonBlockIdExt { workchain: -1, shard: i64::MIN, seqno, root_hash: String::new(), file_hash: String::new() }
+    }
+
+    #[test]
+    fn proof_ends_follow_requested_direction() {
+        let proof = BlocksShardBlockProof { from: block(10), mc_id: block(10), links: vec![], mc_proof: vec![] };
+
+        let (from, to, complete) = proof_ends(&proof, false);
+        assert_eq!((from.seqno, to.seqno, complete), (10, 10, true));
+
+        let proof = BlocksShardBlockProof { from: block(20), mc_id: block(10), links: vec![], mc_proof: vec![] };
+
+        let (from, to, complete) = proof_ends(&proof, false);
+        assert_eq!((from.seqno, to.seqno, complete), (20, 10, false));
+
+        let (from, to, complete) = proof_ends(&proof, true);
+        assert_eq!((from.seqno, to.seqno, complete), (10, 20, false));
+    }
+
+    #[tokio::test]
+    async fn limit_flags_last_item_when_more_follow() {
+        let items = |n: u32| stream::iter((1..=n).map(|i| anyhow::Ok((i, false)))).boxed();
+        let has_more = |item: &mut (u32, bool)| item.1 = true;
+
+        let page: Vec<_> = limit(items(5), Some(2), has_more).try_collect().await.unwrap();
+        assert_eq!(page, vec![(1, false), (2, true)]);
+
+        let page: Vec<_> = limit(items(2), Some(2), has_more).try_collect().await.unwrap();
+        assert_eq!(page, vec![(1, false), (2, false)]);
+
+        let page: Vec<_> = limit(items(3), None, has_more).try_collect().await.unwrap();
+        assert_eq!(page, vec![(1, false), (2, false), (3, false)]);
     }
 }
@@ -0,0 +1,66 @@
+use std::task::{Context, Poll};
+use tonic::codegen::http::Request;
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tower::{Layer, Service};
+use x509_parser::parse_x509_certificate;
+
+/// The subject of the certificate a client authenticated with, set on requests of mTLS connections.
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    pub common_name: String
+}
+
+#[derive(Clone, Default)]
+pub struct ClientIdentityLayer;
+
+impl<S> Layer<S> for ClientIdentityLayer {
+    type Service = ClientIdentityService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClientIdentityService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct ClientIdentityService<S> {
+    inner: S
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for ClientIdentityService<S> where S: Service<Request<ReqBody>> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let identity = req.extensions()
+            .get::<TlsConnectInfo<TcpConnectInfo>>()
+            .and_then(|info| info.peer_certs())
+            .and_then(|certs| certs.first().and_then(|cert| common_name(cert.get_ref())));
+        if let Some(identity) = identity {
+            req.extensions_mut().insert(identity);
+        }
+
+        self.inner.call(req)
+    }
+}
+
+fn common_name(der: &[u8]) -> Option<ClientIdentity> {
+    let (_, cert) = parse_x509_certificate(der).ok()?;
+    let common_name = cert.subject().iter_common_name().next()?.as_str().ok()?;
+
+    Some(ClientIdentity { common_name: common_name.to_owned() })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client_identity::common_name;
+
+    #[test]
+    fn common_name_ignores_invalid_certificates() {
+        assert!(common_name(b"not a certificate").is_none());
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+use tonlibjson_client::block::{BlocksAccountTransactionId, TonBlockIdExt};
+
+#[derive(new, Serialize, Deserialize)]
+pub struct BlockTxCursor {
+    pub block_id: TonBlockIdExt,
+    pub reverse: bool,
+    pub after: BlocksAccountTransactionId
+}
+
+impl BlockTxCursor {
+    pub fn encode(&self) -> Result<String> {
+        Ok(URL_SAFE_NO_PAD.encode(serde_json::to_vec(self)?))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        Ok(serde_json::from_slice(&URL_SAFE_NO_PAD.decode(cursor)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonlibjson_client::block::{BlocksAccountTransactionId, TonBlockIdExt};
+    use crate::cursor::BlockTxCursor;
+
+    #[test]
+    fn cursor_roundtrip() {
+        let block_id = TonBlockIdExt::new(-1, -9223372036854775808, 42, "root".to_owned(), "file".to_owned());
+        let after = BlocksAccountTransactionId { account: "account".to_owned(), lt: 100 };
+
+        let encoded = BlockTxCursor::new(block_id.clone(), true, after).encode().unwrap();
+        let decoded = BlockTxCursor::decode(&encoded).unwrap();
+
+        assert_eq!(decoded.block_id, block_id);
+        assert!(decoded.reverse);
+        assert_eq!(decoded.after.account, "account");
+        assert_eq!(decoded.after.lt, 100);
+    }
+
+    #[test]
+    fn cursor_decode_rejects_garbage() {
+        assert!(BlockTxCursor::decode("not a cursor").is_err());
+    }
+}
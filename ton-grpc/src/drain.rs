@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, Stream, StreamExt};
+use tokio::sync::watch;
+use tonic::Status;
+use crate::error;
+
+#[derive(Clone)]
+pub struct Drain {
+    tx: Arc<watch::Sender<bool>>,
+    active: Arc<AtomicUsize>,
+    unfinished: Arc<AtomicUsize>,
+    drained: Arc<AtomicUsize>,
+    force_closed: Arc<AtomicUsize>,
+    connections: Arc<Mutex<HashMap<Option<SocketAddr>, usize>>>,
+    stream_limit: Option<usize>,
+    shutdown_timeout: Duration
+}
+
+impl Default for Drain {
+    fn default() -> Self {
+        metrics::describe_gauge!("ton_grpc_active_streams", "Number of open server-streaming responses");
+        metrics::describe_counter!("ton_grpc_stream_limit_rejected_total", "Count of streams rejected by the per-client limit");
+
+        Self {
+            tx: Arc::new(watch::Sender::new(false)),
+            active: Default::default(),
+            unfinished: Default::default(),
+            drained: Default::default(),
+            force_closed: Default::default(),
+            connections: Default::default(),
+            stream_limit: None,
+            shutdown_timeout: Duration::ZERO
+        }
+    }
+}
+
+pub struct StreamPermit {
+    connections: Arc<Mutex<HashMap<Option<SocketAddr>, usize>>>,
+    slot: Option<Option<SocketAddr>>
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        let Some(slot) = self.slot else { return };
+
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&slot) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&slot);
+            }
+        }
+    }
+}
+
+impl Drain {
+    pub fn set_stream_limit(mut self, limit: usize) -> Self {
+        self.stream_limit = Some(limit);
+
+        self
+    }
+
+    /// How long open streams may take to finish their current item once draining starts.
+    pub fn set_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+
+        self
+    }
+
+    /// Reserves a stream slot of the client at `remote_addr`, the PROXY source address when the protocol is enabled.
+    /// Clients without an address (e.g. over `--uds`) share a single slot pool.
+    pub fn acquire(&self, remote_addr: Option<SocketAddr>) -> Result<StreamPermit, Status> {
+        let slot = match self.stream_limit {
+            Some(limit) => {
+                let mut connections = self.connections.lock().unwrap();
+                let count = connections.entry(remote_addr).or_default();
+                if *count >= limit {
+                    metrics::counter!("ton_grpc_stream_limit_rejected_total").increment(1);
+
+                    return Err(Status::resource_exhausted(format!("client is limited to {} concurrent streams", limit)));
+                }
+                *count += 1;
+
+                Some(remote_addr)
+            },
+            None => None
+        };
+
+        Ok(StreamPermit { connections: self.connections.clone(), slot })
+    }
+
+    pub fn drain(&self) {
+        self.tx.send_replace(true);
+    }
+
+    pub async fn draining(&self) {
+        let _ = self.tx.subscribe().wait_for(|draining| *draining).await;
+    }
+
+    fn is_draining(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Streams that neither ended nor were closed yet, unlike `active` which counts them until they are dropped.
+    pub fn unfinished(&self) -> usize {
+        self.unfinished.load(Ordering::Relaxed)
+    }
+
+    pub fn drained(&self) -> usize {
+        self.drained.load(Ordering::Relaxed)
+    }
+
+    pub fn force_closed(&self) -> usize {
+        self.force_closed.load(Ordering::Relaxed)
+    }
+
+    pub fn wrap<T: Send + 'static>(&self, permit: StreamPermit, stream: BoxStream<'static, Result<T, Status>>) -> BoxStream<'static, Result<T, Status>> {
+        self.wrap_stream(permit, stream, false)
+    }
+
+    /// Like `wrap` for streams that mostly wait for new items, they end at once when draining starts while idle.
+    pub fn wrap_subscription<T: Send + 'static>(&self, permit: StreamPermit, stream: BoxStream<'static, Result<T, Status>>) -> BoxStream<'static, Result<T, Status>> {
+        self.wrap_stream(permit, stream, true)
+    }
+
+    fn wrap_stream<T: Send + 'static>(&self, permit: StreamPermit, stream: BoxStream<'static, Result<T, Status>>, subscription: bool) -> BoxStream<'static, Result<T, Status>> {
+        let active = self.active.fetch_add(1, Ordering::Relaxed) + 1;
+        metrics::gauge!("ton_grpc_active_streams").set(active as f64);
+        self.unfinished.fetch_add(1, Ordering::Relaxed);
+
+        let this = self.clone();
+        DrainingStream {
+            inner: stream,
+            deadline: async move {
+                this.draining().await;
+                tokio::time::sleep(this.shutdown_timeout).await;
+            }.boxed(),
+            drain: self.clone(),
+            subscription,
+            done: false,
+            _permit: permit
+        }.boxed()
+    }
+}
+
+struct DrainingStream<T> {
+    inner: BoxStream<'static, Result<T, Status>>,
+    deadline: BoxFuture<'static, ()>,
+    drain: Drain,
+    subscription: bool,
+    done: bool,
+    _permit: StreamPermit
+}
+
+impl<T> DrainingStream<T> {
+    fn finish(&mut self) {
+        self.done = true;
+        self.drain.unfinished.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<T> Stream for DrainingStream<T> {
+    type Item = Result<T, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(item) = self.inner.poll_next_unpin(cx) {
+            // once draining, the stream closes cleanly after the item it was working on
+            if item.is_some() && self.drain.is_draining() {
+                self.drain.drained.fetch_add(1, Ordering::Relaxed);
+                self.finish();
+            } else if item.is_none() {
+                self.finish();
+            }
+
+            return Poll::Ready(item);
+        }
+
+        // polling the deadline registers for the start of draining, so an idle subscription is woken to end
+        let deadline = self.deadline.poll_unpin(cx);
+        if self.subscription && self.drain.is_draining() {
+            self.drain.drained.fetch_add(1, Ordering::Relaxed);
+            self.finish();
+
+            return Poll::Ready(None);
+        }
+
+        if deadline.is_ready() {
+            self.drain.force_closed.fetch_add(1, Ordering::Relaxed);
+            self.finish();
+
+            return Poll::Ready(Some(Err(error::unavailable("server is shutting down"))));
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for DrainingStream<T> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.drain.unfinished.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        let active = self.drain.active.fetch_sub(1, Ordering::Relaxed) - 1;
+        metrics::gauge!("ton_grpc_active_streams").set(active as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use futures::{stream, StreamExt};
+    use tonic::Code;
+    use crate::drain::Drain;
+
+    #[tokio::test]
+    async fn stream_completes_without_drain() {
+        let drain = Drain::default();
+
+        let permit = drain.acquire(None).unwrap();
+
+        let items: Vec<_> = drain.wrap(permit, stream::iter([Ok(1), Ok(2)]).boxed()).collect().await;
+
+        assert_eq!(items.into_iter().map(Result::unwrap).collect::<Vec<i32>>(), vec![1, 2]);
+        assert_eq!(drain.active(), 0);
+        assert_eq!(drain.drained(), 0);
+    }
+
+    #[tokio::test]
+    async fn stream_closes_after_current_item_on_drain() {
+        let drain = Drain::default().set_shutdown_timeout(Duration::from_secs(30));
+        let permit = drain.acquire(None).unwrap();
+        let mut stream = drain.wrap(permit, stream::iter([Ok(1), Ok(2), Ok(3)]).boxed());
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+
+        drain.drain();
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), 2);
+        assert!(stream.next().await.is_none());
+        assert_eq!(drain.drained(), 1);
+        assert_eq!(drain.force_closed(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pending_stream_is_force_closed_after_shutdown_timeout() {
+        let drain = Drain::default().set_shutdown_timeout(Duration::from_secs(30));
+        let permit = drain.acquire(None).unwrap();
+        let mut stream = drain.wrap(permit, stream::iter([Ok(1)]).chain(stream::pending()).boxed());
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        assert_eq!(drain.active(), 1);
+
+        drain.drain();
+
+        assert_eq!(stream.next().await.unwrap().unwrap_err().code(), Code::Unavailable);
+        assert!(stream.next().await.is_none());
+        assert_eq!(drain.drained(), 0);
+        assert_eq!(drain.force_closed(), 1);
+        assert_eq!((drain.active(), drain.unfinished()), (1, 0));
+
+        drop(stream);
+        assert_eq!(drain.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn idle_subscription_ends_on_drain() {
+        let drain = Drain::default().set_shutdown_timeout(Duration::from_secs(30));
+        let permit = drain.acquire(None).unwrap();
+        let mut stream = drain.wrap_subscription(permit, stream::iter([Ok::<_, tonic::Status>(1)]).chain(stream::pending()).boxed());
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+
+        let next = tokio::spawn(async move { stream.next().await.is_none() });
+        tokio::task::yield_now().await;
+        drain.drain();
+
+        assert!(next.await.unwrap());
+        assert_eq!((drain.drained(), drain.force_closed(), drain.unfinished()), (1, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn stream_limit_is_per_client() {
+        let drain = Drain::default().set_stream_limit(1);
+        let first = "127.0.0.1:1000".parse().ok();
+        let second = "127.0.0.1:1001".parse().ok();
+
+        let permit = drain.acquire(first).unwrap();
+        assert_eq!(drain.acquire(first).err().unwrap().code(), Code::ResourceExhausted);
+        assert!(drain.acquire(second).is_ok());
+
+        drop(permit);
+        assert!(drain.acquire(first).is_ok());
+    }
+
+    #[tokio::test]
+    async fn clients_without_address_share_stream_limit() {
+        let drain = Drain::default().set_stream_limit(1);
+
+        let permit = drain.acquire(None).unwrap();
+        assert_eq!(drain.acquire(None).err().unwrap().code(), Code::ResourceExhausted);
+
+        drop(permit);
+        assert!(drain.acquire(None).is_ok());
+    }
+}
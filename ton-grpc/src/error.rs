@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+use tonlibjson_client::block::TonError;
+use tonlibjson_client::error::Error;
+
+const DOMAIN: &str = "ton-grpc";
+const RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// INTERNAL with an ErrorInfo, the reason is LITESERVER_ERROR with the liteserver code when tonlib returned one.
+/// Timeouts are UNAVAILABLE with a TIMEOUT reason and a RetryInfo instead, as they are worth retrying,
+/// and so is a pinned liteserver that is not available.
+/// The message carries the whole error chain.
+pub fn internal(e: impl Into<anyhow::Error>) -> Status {
+    let e = e.into();
+    let message = format!("{:#}", e);
+    if e.chain().any(|cause| matches!(cause.downcast_ref::<Error>(), Some(Error::LiteserverUnavailable(_)))) {
+        return unavailable(message)
+    }
+    if Error::is_timeout(&e) {
+        let mut details = ErrorDetails::with_error_info("TIMEOUT", DOMAIN, HashMap::new());
+        details.set_retry_info(Some(RETRY_AFTER));
+
+        return Status::with_error_details(Code::Unavailable, message, details)
+    }
+
+    let details = match Error::ton_error(&e) {
+        Some(error) => ErrorDetails::with_error_info("LITESERVER_ERROR", DOMAIN, [("code".to_owned(), error.code().to_string())]),
+        None => ErrorDetails::with_error_info("INTERNAL", DOMAIN, HashMap::new())
+    };
+
+    Status::with_error_details(Code::Internal, message, details)
+}
+
+/// NOT_FOUND when the liteserver doesn't know the requested object, otherwise the same as `internal`.
+pub fn not_found_or_internal(e: impl Into<anyhow::Error>) -> Status {
+    let e = e.into();
+    if Error::ton_error(&e).is_some_and(TonError::is_not_found) {
+        return Status::not_found(format!("{:#}", e))
+    }
+
+    internal(e)
+}
+
+/// FAILED_PRECONDITION when no connected liteserver retains the requested block anymore, otherwise the same as `internal`.
+pub fn too_deep_or_internal(e: impl Into<anyhow::Error>) -> Status {
+    let e = e.into();
+    if Error::is_too_deep(&e) {
+        return Status::failed_precondition(format!("block is older than the liteservers retain: {:#}", e))
+    }
+
+    internal(e)
+}
+
+/// INVALID_ARGUMENT with a BadRequest naming the offending field.
+pub fn invalid_argument(field: &str, description: impl ToString) -> Status {
+    let description = description.to_string();
+    let details = ErrorDetails::with_bad_request_violation(field, description.clone());
+
+    Status::with_error_details(Code::InvalidArgument, description, details)
+}
+
+/// UNAVAILABLE with a RetryInfo hinting when the request is worth retrying.
+pub fn unavailable(message: impl Into<String>) -> Status {
+    Status::with_error_details(Code::Unavailable, message, ErrorDetails::with_retry_info(Some(RETRY_AFTER)))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use tonic::Code;
+    use tonic_types::StatusExt;
+    use crate::error::{internal, invalid_argument, unavailable};
+
+    #[test]
+    fn internal_carries_error_info() {
+        let status = internal(anyhow!("boom"));
+
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.get_details_error_info().unwrap().reason, "INTERNAL");
+    }
+
+    #[test]
+    fn internal_keeps_error_chain() {
+        let status = internal(anyhow!("block not found").context("cannot load block"));
+
+        assert_eq!(status.message(), "cannot load block: block not found");
+    }
+
+    #[test]
+    fn internal_marks_timeouts_retryable() {
+        let status = internal(anyhow!(tower::timeout::error::Elapsed::new()));
+
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.get_details_error_info().unwrap().reason, "TIMEOUT");
+        assert!(status.get_details_retry_info().unwrap().retry_delay.is_some());
+    }
+
+    #[test]
+    fn internal_marks_unavailable_pinned_liteserver() {
+        let status = internal(anyhow!(tonlibjson_client::error::Error::LiteserverUnavailable("first".to_owned())));
+
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+
+    #[test]
+    fn invalid_argument_names_field() {
+        let status = invalid_argument("account_address", "invalid address");
+
+        let violations = status.get_details_bad_request().unwrap().field_violations;
+        assert_eq!(violations[0].field, "account_address");
+        assert_eq!(status.message(), "invalid address");
+    }
+
+    #[test]
+    fn unavailable_carries_retry_hint() {
+        let status = unavailable("not synchronized");
+
+        assert!(status.get_details_retry_info().unwrap().retry_delay.is_some());
+    }
+}
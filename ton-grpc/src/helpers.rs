@@ -1,6 +1,14 @@
+pub mod abi;
+pub mod address;
+pub mod config;
+pub mod method_id;
+pub mod tx;
+pub mod workchain;
+
 use std::ops::Bound;
 use std::ops::Bound::{Excluded, Included};
 use anyhow::{anyhow, Result};
+use tonic::Request;
 use tonlibjson_client::block;
 use tonlibjson_client::block::InternalTransactionId;
 use tonlibjson_client::ton::TonClient;
@@ -8,6 +16,16 @@ use crate::ton;
 use crate::ton::get_account_transactions_request::bound::Bound::{BlockId, TransactionId};
 use crate::ton::get_account_transactions_request::bound::Type;
 
+pub const MAIN_CHAIN: i32 = -1;
+pub const MAIN_SHARD: i64 = -9223372036854775808;
+
+pub fn no_cache<T>(request: &Request<T>) -> bool {
+    request.metadata()
+        .get("cache-control")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("no-cache"))
+}
+
 #[tracing::instrument(skip_all, err)]
 pub async fn extend_block_id(client: &TonClient, block_id: &ton::BlockId) -> Result<block::TonBlockIdExt> {
     if let (Some(root_hash), Some(file_hash)) = (&block_id.root_hash, &block_id.file_hash) {
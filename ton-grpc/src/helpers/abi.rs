@@ -0,0 +1,69 @@
+use anyhow::{anyhow, Result};
+use num_bigint::BigInt;
+use toner::tlb::r#as::Data;
+use toner::ton::MsgAddress;
+use ton_contract::TvmBoxedStackEntryExt;
+use tonlibjson_client::block::{TvmBoxedStackEntry, TvmCell, TvmSlice, TvmStackEntryCell, TvmStackEntrySlice};
+use crate::ton::{AbiField, AbiType, DecodedValue};
+use crate::ton::decoded_value::Value;
+
+/// Decodes the leading stack entries as described by `abi`, extra entries are ignored.
+pub fn decode(abi: &[AbiField], stack: &[TvmBoxedStackEntry]) -> Result<Vec<DecodedValue>> {
+    if abi.len() > stack.len() {
+        return Err(anyhow!("abi describes {} values, but the stack has only {}", abi.len(), stack.len()));
+    }
+
+    abi.iter()
+        .zip(stack)
+        .map(|(field, entry)| {
+            let value = decode_value(field.r#type(), entry)
+                .map_err(|e| anyhow!("cannot decode {} as {}: {}", field.name, field.r#type().as_str_name(), e))?;
+
+            Ok(DecodedValue { name: field.name.clone(), value: Some(value) })
+        })
+        .collect()
+}
+
+fn decode_value(r#type: AbiType, entry: &TvmBoxedStackEntry) -> Result<Value> {
+    Ok(match r#type {
+        AbiType::Int => Value::Int(entry.to_number::<BigInt>()?.to_string()),
+        AbiType::Bool => Value::Bool(entry.to_number::<BigInt>()? != BigInt::from(0)),
+        AbiType::Address => Value::Address(entry.parse_cell_fully_as::<MsgAddress, Data>()?.to_base64_std()),
+        AbiType::Cell => match entry {
+            TvmBoxedStackEntry::TvmStackEntryCell(TvmStackEntryCell { cell: TvmCell { bytes } })
+            | TvmBoxedStackEntry::TvmStackEntrySlice(TvmStackEntrySlice { slice: TvmSlice { bytes } }) => Value::Cell(bytes.clone()),
+            _ => return Err(anyhow!("expected a cell or a slice"))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tonlibjson_client::block::{TvmBoxedStackEntry, TvmNumberDecimal, TvmStackEntryNumber};
+    use crate::helpers::abi::decode;
+    use crate::ton::{AbiField, AbiType};
+    use crate::ton::decoded_value::Value;
+
+    fn number(value: &str) -> TvmBoxedStackEntry {
+        TvmBoxedStackEntry::TvmStackEntryNumber(TvmStackEntryNumber { number: TvmNumberDecimal { number: value.to_owned() } })
+    }
+
+    fn field(name: &str, r#type: AbiType) -> AbiField {
+        AbiField { name: name.to_owned(), r#type: r#type.into() }
+    }
+
+    #[test]
+    fn decode_numbers() {
+        let decoded = decode(&[field("supply", AbiType::Int), field("mintable", AbiType::Bool)], &[number("-42"), number("-1"), number("7")]).unwrap();
+
+        assert_eq!(decoded[0].name, "supply");
+        assert_eq!(decoded[0].value, Some(Value::Int("-42".to_owned())));
+        assert_eq!(decoded[1].value, Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn decode_rejects_mismatched_stack() {
+        assert!(decode(&[field("a", AbiType::Int), field("b", AbiType::Int)], &[number("1")]).is_err());
+        assert!(decode(&[field("a", AbiType::Cell)], &[number("1")]).is_err());
+    }
+}
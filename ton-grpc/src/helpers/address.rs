@@ -0,0 +1,33 @@
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use tonlibjson_client::address::AccountAddressData;
+
+/// Parses raw and friendly addresses, friendly ones must carry a matching crc16.
+pub fn parse(input: &str) -> Result<AccountAddressData> {
+    let address = AccountAddressData::from_str(input)?;
+    if !address.checksum_matches(input) {
+        return Err(anyhow!("invalid address checksum"));
+    }
+
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::address::parse;
+
+    #[test]
+    fn parse_accepts_all_forms() {
+        let raw = parse("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zj").unwrap().to_raw_string();
+
+        assert_eq!(parse(&raw).unwrap().to_raw_string(), raw);
+        assert_eq!(parse("EQCkgtq1pKJh4Zpif/z4RR2aYmespuImTw15amEacGX+k6Zj").unwrap().to_raw_string(), raw);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_addresses() {
+        assert_eq!(parse("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX-k6Zk").unwrap_err().to_string(), "invalid address checksum");
+        assert!(parse("0:00").is_err());
+        assert!(parse("EQCkgtq1pKJh4Zpif_z4RR2aYmespuImTw15amEacGX").is_err());
+    }
+}
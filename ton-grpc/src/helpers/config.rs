@@ -0,0 +1,280 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use toner::tlb::bits::de::{unpack_bytes, BitReaderExt};
+use toner::tlb::de::{CellDeserialize, CellParser, CellParserError};
+use toner::tlb::r#as::Ref;
+use toner::tlb::Cell;
+use toner::ton::boc::BoC;
+use crate::ton::get_config_param_response::{Decoded, ElectionTimings, GasPrices, Validator, Validators};
+
+impl<'de> CellDeserialize<'de> for ElectionTimings {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        Ok(Self {
+            validators_elected_for: parser.unpack()?,
+            elections_start_before: parser.unpack()?,
+            elections_end_before: parser.unpack()?,
+            stake_held_for: parser.unpack()?
+        })
+    }
+}
+
+impl<'de> CellDeserialize<'de> for GasPrices {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        let tag: u8 = parser.unpack()?;
+
+        // gas_flat_pfx#d1 flat_gas_limit:uint64 flat_gas_price:uint64 other:GasLimitsPrices
+        if tag == 0xd1 {
+            let flat_gas_limit: u64 = parser.unpack()?;
+            let flat_gas_price: u64 = parser.unpack()?;
+            let other = Self::parse(parser)?;
+
+            return Ok(Self { flat_gas_limit: Some(flat_gas_limit), flat_gas_price: Some(flat_gas_price), ..other });
+        }
+
+        // gas_prices#dd gas_price gas_limit gas_credit ..., gas_prices_ext#de adds special_gas_limit after gas_limit
+        let gas_price = parser.unpack()?;
+        let gas_limit = parser.unpack()?;
+        let special_gas_limit = match tag {
+            0xdd => None,
+            0xde => Some(parser.unpack()?),
+            _ => return Err(CellParserError::custom("unknown gas prices tag"))
+        };
+
+        Ok(Self {
+            flat_gas_limit: None,
+            flat_gas_price: None,
+            gas_price,
+            gas_limit,
+            special_gas_limit,
+            gas_credit: parser.unpack()?,
+            block_gas_limit: parser.unpack()?,
+            freeze_due_limit: parser.unpack()?,
+            delete_due_limit: parser.unpack()?
+        })
+    }
+}
+
+impl<'de> CellDeserialize<'de> for Validators {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        // validators#11 utime_since utime_until total main list, validators_ext#12 adds total_weight before list
+        let tag: u8 = parser.unpack()?;
+        if tag != 0x11 && tag != 0x12 {
+            return Err(CellParserError::custom("unknown validator set tag"));
+        }
+
+        let utime_since = parser.unpack()?;
+        let utime_until = parser.unpack()?;
+        let total: u16 = parser.unpack()?;
+        let main: u16 = parser.unpack()?;
+        let total_weight = if tag == 0x12 { Some(parser.unpack()?) } else { None };
+        let list = validator_list(parser).map_err(CellParserError::custom)?;
+
+        Ok(Self { utime_since, utime_until, total: total as u32, main: main as u32, total_weight, list })
+    }
+}
+
+impl<'de> CellDeserialize<'de> for Validator {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        // validator#53 public_key:SigPubKey weight:uint64, validator_addr#73 adds adnl_addr:bits256
+        let tag: u8 = parser.unpack()?;
+        if tag != 0x53 && tag != 0x73 {
+            return Err(CellParserError::custom("unknown validator tag"));
+        }
+
+        // ed25519_pubkey#8e81278a pubkey:bits256
+        let key_tag: u32 = parser.unpack()?;
+        if key_tag != 0x8e81278a {
+            return Err(CellParserError::custom("unknown public key tag"));
+        }
+
+        let public_key: [u8; 32] = parser.unpack()?;
+        let weight = parser.unpack()?;
+        let adnl_addr = if tag == 0x73 { Some(STANDARD.encode(parser.unpack::<[u8; 32]>()?)) } else { None };
+
+        Ok(Self { public_key: STANDARD.encode(public_key), weight, adnl_addr })
+    }
+}
+
+/// Reads `list:(HashmapE 16 ValidatorDescr)`, the keys are the validator indexes.
+fn validator_list(parser: &mut CellParser<'_>) -> Result<Vec<Validator>> {
+    let mut list = Vec::new();
+
+    let present: bool = parser.unpack().map_err(|e| anyhow!("{}", e))?;
+    if present {
+        let root: Cell = parser.parse_as::<_, Ref>().map_err(|e| anyhow!("{}", e))?;
+        hashmap_values(&root, 16, &mut list)?;
+    }
+
+    Ok(list)
+}
+
+/// Collects the values of a `Hashmap n X` node in key order.
+fn hashmap_values(cell: &Cell, n: u32, values: &mut Vec<Validator>) -> Result<()> {
+    let mut parser = cell.parser();
+
+    let label = hashmap_label(&mut parser, n)?;
+    if label == n {
+        values.push(parser.parse().map_err(|e| anyhow!("{}", e))?);
+
+        return Ok(());
+    }
+
+    // hmn_fork left:^(Hashmap n X) right:^(Hashmap n X), the fork consumes one more key bit
+    let left: Cell = parser.parse_as::<_, Ref>().map_err(|e| anyhow!("{}", e))?;
+    let right: Cell = parser.parse_as::<_, Ref>().map_err(|e| anyhow!("{}", e))?;
+    hashmap_values(&left, n - label - 1, values)?;
+    hashmap_values(&right, n - label - 1, values)
+}
+
+/// Skips a `HmLabel ~l n` and returns its length `l`.
+fn hashmap_label(parser: &mut CellParser<'_>, n: u32) -> Result<u32> {
+    let len = if !bit(parser)? {
+        // hml_short$0 len:(Unary ~n) s:(n * Bit)
+        let mut len = 0;
+        while bit(parser)? {
+            len += 1;
+        }
+        skip(parser, len)?;
+
+        len
+    } else if !bit(parser)? {
+        // hml_long$10 n:(#<= m) s:(n * Bit)
+        let len = uint_at_most(parser, n)?;
+        skip(parser, len)?;
+
+        len
+    } else {
+        // hml_same$11 v:Bit n:(#<= m)
+        bit(parser)?;
+
+        uint_at_most(parser, n)?
+    };
+
+    if len > n {
+        return Err(anyhow!("hashmap label is longer than the key"));
+    }
+
+    Ok(len)
+}
+
+fn bit(parser: &mut CellParser<'_>) -> Result<bool> {
+    parser.unpack().map_err(|e| anyhow!("{}", e))
+}
+
+fn skip(parser: &mut CellParser<'_>, len: u32) -> Result<()> {
+    for _ in 0..len {
+        bit(parser)?;
+    }
+
+    Ok(())
+}
+
+/// Reads `#<= max`, which takes as many bits as `max` does.
+fn uint_at_most(parser: &mut CellParser<'_>, max: u32) -> Result<u32> {
+    let mut value = 0;
+    for _ in 0..u32::BITS - max.leading_zeros() {
+        value = value << 1 | bit(parser)? as u32;
+    }
+
+    Ok(value)
+}
+
+/// Decodes the well known config params from a base64 encoded boc, other params are left to the caller.
+pub fn decode(config_id: i32, data: &str) -> Result<Option<Decoded>> {
+    if !matches!(config_id, 15 | 20 | 21 | 32..=37) {
+        return Ok(None);
+    }
+
+    let boc: BoC = unpack_bytes(STANDARD.decode(data)?)
+        .map_err(|e| anyhow!("invalid boc: {}", e))?;
+    let root = boc.single_root()
+        .ok_or_else(|| anyhow!("boc must contain a single root cell"))?;
+
+    let decoded = match config_id {
+        15 => root.parse().map(Decoded::ElectionTimings),
+        20 | 21 => root.parse().map(Decoded::GasPrices),
+        _ => root.parse().map(Decoded::Validators)
+    }.map_err(|e| anyhow!("invalid config param {}: {}", config_id, e))?;
+
+    Ok(Some(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::config::decode;
+    use crate::ton::get_config_param_response::{Decoded, ElectionTimings, GasPrices, Validator, Validators};
+
+    const ELECTION_TIMINGS: &str = "te6ccgEBAQEAEgAAIAABAAAAAIAAAAAgAAAAgAA=";
+    const MASTERCHAIN_GAS_PRICES: &str = "te6ccgEBAQEATAAAlNEAAAAAAAAAZAAAAAAAD0JA3gAAAAAnEAAAAAAAAAAPQkAAAAAABCwdgAAAAAAAACcQAAAAAAAmJaAAAAAABfXhAAAAAAA7msoA";
+    const BASECHAIN_GAS_PRICES: &str = "te6ccgEBAQEATAAAlNEAAAAAAAAAZAAAAAAAAJxA3gAAAAABkAAAAAAAAAAPQkAAAAAAAA9CQAAAAAAAACcQAAAAAACYloAAAAAABfXhAAAAAAA7msoA";
+    // validators_ext with a validator#53 at key 0 and a validator_addr#73 at key 1
+    const VALIDATORS: &str = "te6ccgEBBAEAngABKxJlU/EAZVTxAAACAAIAAAAAAAAAHsABAgLPAgMAWxTjoEnigEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAAAAAAAAAAqAAmxzjoEnigICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAAAAAAAAABQDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDA4A==";
+
+    #[test]
+    fn decode_election_timings() {
+        let decoded = decode(15, ELECTION_TIMINGS).unwrap().unwrap();
+
+        assert_eq!(decoded, Decoded::ElectionTimings(ElectionTimings {
+            validators_elected_for: 65536,
+            elections_start_before: 32768,
+            elections_end_before: 8192,
+            stake_held_for: 32768
+        }));
+    }
+
+    #[test]
+    fn decode_gas_prices() {
+        let decoded = decode(20, MASTERCHAIN_GAS_PRICES).unwrap().unwrap();
+
+        assert_eq!(decoded, Decoded::GasPrices(GasPrices {
+            flat_gas_limit: Some(100),
+            flat_gas_price: Some(1000000),
+            gas_price: 655360000,
+            gas_limit: 1000000,
+            special_gas_limit: Some(70000000),
+            gas_credit: 10000,
+            block_gas_limit: 2500000,
+            freeze_due_limit: 100000000,
+            delete_due_limit: 1000000000
+        }));
+
+        let Decoded::GasPrices(prices) = decode(21, BASECHAIN_GAS_PRICES).unwrap().unwrap() else {
+            panic!("expected gas prices");
+        };
+        assert_eq!((prices.flat_gas_price, prices.gas_price, prices.block_gas_limit), (Some(40000), 26214400, 10000000));
+    }
+
+    #[test]
+    fn decode_validators_with_list() {
+        let expected = Decoded::Validators(Validators {
+            utime_since: 1700000000,
+            utime_until: 1700065536,
+            total: 2,
+            main: 2,
+            total_weight: Some(30),
+            list: vec![
+                Validator { public_key: "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=".to_owned(), weight: 10, adnl_addr: None },
+                Validator {
+                    public_key: "AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI=".to_owned(),
+                    weight: 20,
+                    adnl_addr: Some("AwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwM=".to_owned())
+                }
+            ]
+        });
+
+        assert_eq!(decode(34, VALIDATORS).unwrap().unwrap(), expected);
+        assert_eq!(decode(37, VALIDATORS).unwrap().unwrap(), expected);
+    }
+
+    #[test]
+    fn decode_skips_unknown_params() {
+        assert!(decode(0, "AAAA").unwrap().is_none());
+    }
+
+    #[test]
+    fn decode_rejects_invalid_boc() {
+        let error = decode(34, "AAAA").unwrap_err();
+
+        assert!(error.to_string().starts_with("invalid boc"));
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::{anyhow, Result};
+use crc::Crc;
+
+const CRC16: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_XMODEM);
+const MIN_METHOD_ID: i64 = -(1 << 18);
+const MAX_METHOD_ID: i64 = (1 << 18) - 1;
+
+pub fn compute(name: &str) -> i64 {
+    (CRC16.checksum(name.as_bytes()) as i64) | 0x10000
+}
+
+pub fn validate(id: i64) -> Result<i32> {
+    if !(MIN_METHOD_ID..=MAX_METHOD_ID).contains(&id) {
+        return Err(anyhow!("method id {} is out of the 19-bit signed range", id));
+    }
+
+    Ok(id as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::method_id::{compute, validate};
+
+    #[test]
+    fn computes_well_known_method_ids() {
+        assert_eq!(compute("seqno"), 85143);
+        assert_eq!(compute("get_public_key"), 78748);
+        assert_eq!(compute("get_jetton_data"), 106029);
+        assert_eq!(compute("get_wallet_address"), 103289);
+    }
+
+    #[test]
+    fn validates_method_id_range() {
+        assert_eq!(validate(85143).unwrap(), 85143);
+        assert_eq!(validate(-1).unwrap(), -1);
+        assert!(validate(1 << 18).is_err());
+        assert!(validate(i64::MIN).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use toner::tlb::bits::de::{unpack_bytes, BitReaderExt};
+use toner::tlb::de::{CellDeserialize, CellParser, CellParserError};
+use toner::ton::boc::BoC;
+use crate::ton::TransactionType;
+
+struct Description(TransactionType);
+
+impl<'de> CellDeserialize<'de> for Description {
+    fn parse(parser: &mut CellParser<'de>) -> Result<Self, CellParserError<'de>> {
+        let tag: [bool; 3] = [parser.unpack()?, parser.unpack()?, parser.unpack()?];
+        let last: bool = parser.unpack()?;
+
+        // trans_ord$0000 trans_storage$0001 trans_tick_tock$001 is_tock:Bool trans_split_prepare$0100 ...
+        let r#type = match (tag, last) {
+            ([false, false, false], false) => TransactionType::Ordinary,
+            ([false, false, false], true) => TransactionType::Storage,
+            ([false, false, true], false) => TransactionType::Tick,
+            ([false, false, true], true) => TransactionType::Tock,
+            ([false, true, false], false) => TransactionType::SplitPrepare,
+            ([false, true, false], true) => TransactionType::SplitInstall,
+            ([false, true, true], false) => TransactionType::MergePrepare,
+            ([false, true, true], true) => TransactionType::MergeInstall,
+            _ => return Err(CellParserError::custom("unknown transaction description tag"))
+        };
+
+        Ok(Self(r#type))
+    }
+}
+
+/// Reads the description type of a base64 encoded transaction boc, the description is the last reference of the root cell.
+pub fn parse_type(data: &str) -> Result<TransactionType> {
+    let boc: BoC = unpack_bytes(STANDARD.decode(data)?)
+        .map_err(|e| anyhow!("invalid boc: {}", e))?;
+    let root = boc.single_root()
+        .ok_or_else(|| anyhow!("boc must contain a single root cell"))?;
+    let description = root.references.last()
+        .ok_or_else(|| anyhow!("transaction has no description"))?;
+
+    let Description(r#type) = description.parse()
+        .map_err(|e| anyhow!("invalid transaction description: {}", e))?;
+
+    Ok(r#type)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::tx::parse_type;
+    use crate::ton::TransactionType;
+
+    // transactions with the in_msg/out_msgs, state_update and description references
+    const ORDINARY: &str = "te6ccgEBBAEAqgADt3BgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYAACq/Ck3wAQcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHAAAqvwpN8ABlU/EAAAFIBfXhAIAQIDAAEgAIJyBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQACAA==";
+    const TICK: &str = "te6ccgEBBAEAqgADt3BgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYGBgYAACq/Ck3wAQcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHAAAqvwpN8ABlU/EAAAFIBfXhAIAQIDAAEgAIJyBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQABIg==";
+
+    #[test]
+    fn parse_type_reads_description() {
+        assert_eq!(parse_type(ORDINARY).unwrap(), TransactionType::Ordinary);
+        assert_eq!(parse_type(TICK).unwrap(), TransactionType::Tick);
+    }
+
+    #[test]
+    fn parse_type_rejects_invalid_boc() {
+        let error = parse_type("AAAA").unwrap_err();
+
+        assert!(error.to_string().starts_with("invalid boc"));
+    }
+}
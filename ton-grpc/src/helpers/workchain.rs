@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use tonic::Status;
+use tonlibjson_client::address::AccountAddressData;
+use crate::error;
+use crate::helpers::address;
+
+const MASTERCHAIN: i32 = -1;
+const BASECHAIN: i32 = 0;
+
+#[derive(Debug, Clone)]
+pub struct AllowedWorkchains {
+    workchains: Arc<Vec<i32>>
+}
+
+impl Default for AllowedWorkchains {
+    fn default() -> Self {
+        Self::new(vec![MASTERCHAIN, BASECHAIN])
+    }
+}
+
+impl AllowedWorkchains {
+    pub fn new(workchains: Vec<i32>) -> Self {
+        Self { workchains: Arc::new(workchains) }
+    }
+
+    pub fn check(&self, workchain: i32) -> Result<(), Status> {
+        if self.workchains.contains(&workchain) {
+            return Ok(())
+        }
+
+        Err(error::invalid_argument("workchain", format!("workchain {} is not supported, expected one of {:?}", workchain, self.workchains)))
+    }
+
+    pub fn parse_address(&self, field: &str, address: &str) -> Result<AccountAddressData, Status> {
+        let address = address::parse(address)
+            .map_err(|e| error::invalid_argument(field, e))?;
+        self.check(address.chain_id)?;
+
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+    use crate::helpers::workchain::AllowedWorkchains;
+
+    #[test]
+    fn default_allows_masterchain_and_basechain() {
+        let workchains = AllowedWorkchains::default();
+
+        assert!(workchains.check(-1).is_ok());
+        assert!(workchains.check(0).is_ok());
+        assert_eq!(workchains.check(1).unwrap_err().code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn basechain_only_rejects_masterchain_address() {
+        let workchains = AllowedWorkchains::new(vec![0]);
+
+        assert!(workchains.parse_address("account_address", "0:0000000000000000000000000000000000000000000000000000000000000000").is_ok());
+        assert!(workchains.parse_address("account_address", "-1:3333333333333333333333333333333333333333333333333333333333333333").is_err());
+        assert_eq!(workchains.parse_address("account_address", "not an address").unwrap_err().code(), Code::InvalidArgument);
+    }
+}
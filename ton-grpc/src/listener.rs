@@ -0,0 +1,247 @@
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use anyhow::Result;
+use futures::Stream;
+use socket2::{Domain, Protocol, SockRef, Socket, TcpKeepalive, Type};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc::UnboundedSender;
+use tonic::transport::server::{Connected, TcpConnectInfo};
+use crate::proxy_protocol::{Header, read_header};
+
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+const BACKLOG: i32 = 1024;
+/// Pause after a failed accept, e.g. when the process ran out of file descriptors, as hyper's AddrIncoming does.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+enum Transport {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream)
+}
+
+pub struct Connection {
+    inner: Transport,
+    local_addr: Option<SocketAddr>,
+    remote_addr: Option<SocketAddr>
+}
+
+impl Connection {
+    async fn accept(mut inner: TcpStream, proxy_protocol: bool) -> Result<Self> {
+        let header = if proxy_protocol {
+            tokio::time::timeout(PROXY_HEADER_TIMEOUT, read_header(&mut inner)).await??
+        } else {
+            Header::Local
+        };
+
+        let (local_addr, remote_addr) = match header {
+            Header::Proxied { source, destination } => (Some(destination), Some(source)),
+            Header::Local => (inner.local_addr().ok(), inner.peer_addr().ok())
+        };
+        let canonical = |addr: SocketAddr| SocketAddr::new(addr.ip().to_canonical(), addr.port());
+
+        Ok(Self { inner: Transport::Tcp(inner), local_addr: local_addr.map(canonical), remote_addr: remote_addr.map(canonical) })
+    }
+
+    #[cfg(unix)]
+    fn unix(inner: UnixStream) -> Self {
+        Self { inner: Transport::Unix(inner), local_addr: None, remote_addr: None }
+    }
+}
+
+impl Connected for Connection {
+    type ConnectInfo = TcpConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        TcpConnectInfo { local_addr: self.local_addr, remote_addr: self.remote_addr }
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match &mut self.inner {
+            Transport::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_read(cx, buf)
+        }
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match &mut self.inner {
+            Transport::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_write(cx, buf)
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.inner {
+            Transport::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_flush(cx)
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.inner {
+            Transport::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_shutdown(cx)
+        }
+    }
+}
+
+/// `[::]` accepts IPv4 connections too unless `only_v6`, so it doesn't clash with a separate IPv4 listener.
+fn bind(addr: SocketAddr, only_v6: bool) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(only_v6)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(BACKLOG)?;
+
+    TcpListener::from_std(socket.into())
+}
+
+/// A socket file left behind by a previous run is removed, any other file at `path` makes binding fail.
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+
+    UnixListener::bind(path)
+}
+
+/// Accepts on the unix socket `uds` when given, otherwise on every address of `addrs`.
+pub fn incoming(addrs: &[SocketAddr], uds: Option<&Path>, proxy_protocol: bool, tcp_keepalive: Duration) -> Result<impl Stream<Item = io::Result<Connection>>> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    if let Some(path) = uds {
+        listen_unix(path, tx)?;
+    } else {
+        let only_v6 = addrs.iter().any(SocketAddr::is_ipv4);
+        for addr in addrs {
+            let listener = bind(*addr, only_v6)?;
+            tracing::info!("Listening on {:?}", addr);
+
+            tokio::spawn(accept_loop(listener, tx.clone(), proxy_protocol, tcp_keepalive));
+        }
+    }
+
+    Ok(futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|connection| (connection, rx))
+    }))
+}
+
+#[cfg(unix)]
+fn listen_unix(path: &Path, tx: UnboundedSender<io::Result<Connection>>) -> Result<()> {
+    let listener = bind_unix(path)?;
+    tracing::info!("Listening on {:?}", path);
+
+    tokio::spawn(accept_unix_loop(listener, tx));
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen_unix(path: &Path, _tx: UnboundedSender<io::Result<Connection>>) -> Result<()> {
+    anyhow::bail!("cannot listen on {:?}, unix domain sockets are only supported on unix", path)
+}
+
+#[cfg(unix)]
+async fn accept_unix_loop(listener: UnixListener, tx: UnboundedSender<io::Result<Connection>>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => { let _ = tx.send(Ok(Connection::unix(stream))); },
+            Err(e) => accept_error(e).await
+        }
+    }
+}
+
+/// Errors of a single connection don't affect the listener, any other error is retried after a pause.
+async fn accept_error(e: io::Error) {
+    if matches!(e.kind(), io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset) {
+        return;
+    }
+
+    tracing::error!(error = ?e, "failed to accept connection");
+    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+}
+
+async fn accept_loop(listener: TcpListener, tx: UnboundedSender<io::Result<Connection>>, proxy_protocol: bool, tcp_keepalive: Duration) {
+    loop {
+        let (stream, peer_addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                accept_error(e).await;
+                continue;
+            }
+        };
+        let _ = stream.set_nodelay(true);
+        let _ = SockRef::from(&stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(tcp_keepalive));
+
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            match Connection::accept(stream, proxy_protocol).await {
+                Ok(connection) => { let _ = tx.send(Ok(connection)); },
+                Err(e) => tracing::warn!(peer_addr = ?peer_addr, error = ?e, "rejected connection without a valid PROXY protocol header")
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tokio::net::TcpStream;
+    use tonic::transport::server::Connected;
+    use crate::listener::{accept_loop, bind};
+
+    #[tokio::test]
+    async fn accepts_tcp_connections() {
+        let listener = bind("127.0.0.1:0".parse().unwrap(), false).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(accept_loop(listener, tx, false, Duration::from_secs(60)));
+
+        let client = TcpStream::connect(addr).await.unwrap();
+        let connection = rx.recv().await.unwrap().unwrap();
+
+        let info = connection.connect_info();
+        assert_eq!(info.local_addr, Some(addr));
+        assert_eq!(info.remote_addr, client.local_addr().ok());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn accepts_unix_connections_and_replaces_stale_socket() {
+        use tokio::net::UnixStream;
+        use crate::listener::{accept_unix_loop, bind_unix};
+
+        let path = std::env::temp_dir().join(format!("ton-grpc-listener-{}.sock", std::process::id()));
+        drop(bind_unix(&path).unwrap());
+
+        let listener = bind_unix(&path).unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(accept_unix_loop(listener, tx));
+
+        let _client = UnixStream::connect(&path).await.unwrap();
+        let connection = rx.recv().await.unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(connection.connect_info().remote_addr, None);
+    }
+}
@@ -0,0 +1,106 @@
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tonic::body::BoxBody;
+use tonic::codegen::{Body, Bytes};
+use tonic::codegen::http::{HeaderMap, HeaderValue, Request, Response};
+use tonic::Status;
+use tower::{Layer, Service};
+use tonlibjson_client::liteserver::LiteserverScope;
+use crate::error;
+
+const LITESERVER_HEADER: &str = "x-ton-liteserver";
+
+#[derive(Clone, Default)]
+pub struct LiteserverLayer;
+
+impl<S> Layer<S> for LiteserverLayer {
+    type Service = LiteserverService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LiteserverService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct LiteserverService<S> {
+    inner: S
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for LiteserverService<S>
+    where S: Service<Request<ReqBody>, Response = Response<BoxBody>>,
+          S::Future: Send + 'static,
+          S::Error: Send + 'static {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let scope = req.headers()
+            .get(LITESERVER_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|id| LiteserverScope::pinned(id.to_owned()))
+            .unwrap_or_default();
+        let future = scope.sync_scope(|| self.inner.call(req));
+
+        async move {
+            let response = scope.scope(future).await;
+            if let Err(e) = scope.check() {
+                return Ok(error::unavailable(e.to_string()).to_http())
+            }
+            let mut response = response?;
+
+            // a trailers-only response has no body to carry the trailer, its headers are the trailers
+            if response.headers().contains_key("grpc-status") {
+                if let Some(value) = served_by(&scope) {
+                    response.headers_mut().insert(LITESERVER_HEADER, value);
+                }
+            }
+            response.extensions_mut().insert(scope.clone());
+
+            Ok(response.map(|body| tonic::body::boxed(LiteserverBody { inner: body, scope })))
+        }.boxed()
+    }
+}
+
+fn served_by(scope: &LiteserverScope) -> Option<HeaderValue> {
+    scope.served_by().and_then(|id| HeaderValue::from_str(&id).ok())
+}
+
+/// Streaming responses do their lookups while the body is polled, so the body keeps the scope of its request
+/// and reports the liteserver in the trailers once it is finished.
+struct LiteserverBody {
+    inner: BoxBody,
+    scope: LiteserverScope
+}
+
+impl Body for LiteserverBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = &mut *self;
+
+        this.scope.sync_scope(|| Pin::new(&mut this.inner).poll_data(cx))
+    }
+
+    fn poll_trailers(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = &mut *self;
+        let mut trailers = ready!(this.scope.sync_scope(|| Pin::new(&mut this.inner).poll_trailers(cx)))?;
+
+        if let Some(value) = served_by(&this.scope) {
+            trailers.get_or_insert_with(HeaderMap::new).insert(LITESERVER_HEADER, value);
+        }
+
+        Poll::Ready(Ok(trailers))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+}
@@ -3,30 +3,91 @@ mod account;
 mod helpers;
 mod block;
 mod message;
+mod liteserver;
+mod sync;
+mod panic;
+mod cursor;
+mod drain;
+mod server;
+mod slow;
+mod metric;
+#[cfg(unix)]
+mod reload;
+mod trace;
+mod rate_limit;
+mod proxy_protocol;
+mod listener;
+mod reflection;
+mod admin;
+mod error;
+mod client_identity;
 
+use std::num::NonZeroUsize;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
-use metrics_exporter_prometheus::PrometheusBuilder;
-use tonic::transport::Server;
-use tonic::codec::CompressionEncoding::Gzip;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use metrics_exporter_statsd::StatsdBuilder;
+use anyhow::Context;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tonic::codec::CompressionEncoding;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::fmt::format::FmtSpan;
+use ton_contract::wallet::WalletVersion;
+use tonlibjson_client::retry::RetryBackoff;
 use tonlibjson_client::ton::TonClientBuilder;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use url::Url;
-use crate::account::AccountService;
+use crate::account::{AccountService, KnownCodeHashes};
 use crate::block::BlockService;
+use crate::client_identity::ClientIdentityLayer;
+use crate::drain::Drain;
+use crate::helpers::workchain::AllowedWorkchains;
+use crate::liteserver::LiteserverLayer;
 use crate::message::MessageService;
+use crate::metric::RequestDurationLayer;
+use crate::rate_limit::{RateLimitLayer, TokenBucket};
+use crate::panic::CatchPanicLayer;
+use crate::reflection::ReflectionV1Layer;
+use crate::admin::{check_api_key, AdminService};
+use crate::server::ServerService;
+use crate::slow::SlowRequestLayer;
+use crate::sync::SyncedLayer;
 use crate::ton::account_service_server::AccountServiceServer;
+use crate::ton::admin_service_server::AdminServiceServer;
 use crate::ton::block_service_server::BlockServiceServer;
 use crate::ton::message_service_server::MessageServiceServer;
+use crate::ton::server_service_server::ServerServiceServer;
+
+/// Time for streams force-closed at the shutdown timeout to deliver their final status before the process exits.
+const FORCE_CLOSE_GRACE: Duration = Duration::from_secs(1);
 
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     #[clap(long, default_value = "0.0.0.0:50052")]
-    listen: SocketAddr,
+    listen: Vec<SocketAddr>,
+    #[clap(long, conflicts_with = "listen")]
+    uds: Option<PathBuf>,
+    #[clap(long)]
+    proxy_protocol: bool,
+    #[clap(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    #[clap(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    #[clap(long, requires = "tls_cert")]
+    tls_client_ca: Option<PathBuf>,
+    #[clap(long)]
+    disable_reflection: bool,
+    #[clap(long, env = "ADMIN_API_KEY")]
+    admin_api_key: Option<String>,
+    #[clap(long, env = "WORKER_THREADS")]
+    worker_threads: Option<NonZeroUsize>,
+    #[clap(long)]
+    dump_descriptors: Option<PathBuf>,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "30s")]
     timeout: Duration,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "300s")]
@@ -35,18 +96,58 @@ struct Args {
     http2_keepalive_interval: Duration,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "20s")]
     http2_keepalive_timeout: Duration,
-    #[clap(long, default_value = "65535")]
+    /// HTTP/2 flow-control window shared by all streams of a connection. Larger windows speed up
+    /// big streaming responses on fast links, but each connection may buffer up to this many bytes.
+    #[clap(long, alias = "http2-initial-connection-window-size", default_value = "65535")]
     initial_connection_window_size: u32,
-    #[clap(long, default_value = "65535")]
+    /// HTTP/2 flow-control window of a single stream, memory per connection grows up to
+    /// this times the number of concurrent streams, capped by the connection window.
+    #[clap(long, alias = "http2-initial-stream-window-size", default_value = "65535")]
     initial_stream_window_size: u32,
+    #[clap(long, default_value_t = 4 * 1024 * 1024)]
+    max_request_bytes: usize,
+    /// Limit of concurrent server streams per client address.
+    /// Clients without an address, e.g. every client of a unix socket listener, share one limit.
+    #[clap(long)]
+    max_streams_per_connection: Option<NonZeroUsize>,
+    #[clap(long, value_parser = parse_positive)]
+    rate_limit_rps: Option<f64>,
+    #[clap(long, value_parser = parse_positive)]
+    rate_limit_burst: Option<f64>,
+    #[clap(long, value_parser = parse_method_value)]
+    method_rate_limit: Vec<(String, f64)>,
+    #[clap(long, value_parser = parse_method_value)]
+    method_weight: Vec<(String, f64)>,
+    #[clap(long, value_parser = humantime::parse_duration)]
+    slow_request_threshold: Option<Duration>,
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "30s")]
+    shutdown_timeout: Duration,
+    #[clap(long)]
+    debug_trace: bool,
+    #[clap(long, default_value_t = 4096)]
+    debug_trace_max_size: usize,
+
+    /// Encoding of responses, requests are accepted gzip or zstd compressed either way
+    #[clap(long, value_enum, default_value_t = Compression::Gzip)]
+    compression: Compression,
 
     #[clap(long)]
     enable_metrics: bool,
+    #[clap(long, value_enum, default_value_t = MetricsBackend::Prometheus)]
+    metrics_backend: MetricsBackend,
     #[clap(long, default_value = "0.0.0.0:9000")]
     metrics_listen: SocketAddr,
+    #[clap(long, default_value = "127.0.0.1")]
+    statsd_host: String,
+    #[clap(long, default_value_t = 8125)]
+    statsd_port: u16,
+    #[clap(long, value_delimiter = ',', default_value = "0.005,0.01,0.025,0.05,0.1,0.25,0.5,1,2.5,5,10")]
+    metrics_buckets: Vec<f64>,
 
     #[clap(long, value_parser = Url::parse, default_value_t = tonlibjson_client::ton::default_ton_config_url())]
     ton_config_url: Url,
+    #[clap(long, value_parser = Url::parse)]
+    archival_ton_config_url: Option<Url>,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
     ton_timeout: Duration,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
@@ -59,84 +160,402 @@ struct Args {
     retry_first_delay: Duration,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "4096ms")]
     retry_max_delay: Duration,
+    #[clap(long, default_value = "fibonacci")]
+    retry_backoff: RetryBackoff,
+    #[clap(long)]
+    retry_config: Option<PathBuf>,
 
     #[clap(long, value_parser = humantime::parse_duration, default_value = "70ms")]
     ewma_default_rtt: Duration,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "1ms")]
-    ewma_decay: Duration
+    ewma_decay: Duration,
+
+    #[clap(long, value_parser = humantime::parse_duration)]
+    account_state_cache_ttl: Option<Duration>,
+    #[clap(long, default_value_t = 8192)]
+    account_state_cache_capacity: usize,
+    #[clap(long, value_parser = humantime::parse_duration)]
+    config_cache_ttl: Option<Duration>,
+    #[clap(long, default_value_t = 1024)]
+    config_cache_capacity: usize,
+    #[clap(long, value_parser = humantime::parse_duration)]
+    get_method_cache_ttl: Option<Duration>,
+    #[clap(long, default_value_t = 8192)]
+    get_method_cache_capacity: usize,
+    #[clap(long, value_parser = humantime::parse_duration)]
+    shards_cache_ttl: Option<Duration>,
+    #[clap(long, default_value_t = 1024)]
+    shards_cache_capacity: usize,
+    #[clap(long, value_parser = humantime::parse_duration)]
+    send_message_dedup_ttl: Option<Duration>,
+    #[clap(long, default_value_t = 8192)]
+    send_message_dedup_capacity: usize,
+    #[clap(long, value_parser = humantime::parse_duration)]
+    idempotency_key_ttl: Option<Duration>,
+    #[clap(long, default_value_t = 8192)]
+    idempotency_key_capacity: usize,
+    #[clap(long, value_parser = humantime::parse_duration)]
+    masterchain_info_cache_ttl: Option<Duration>,
+
+    #[clap(long)]
+    liteserver_blacklist_failures: Option<usize>,
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "60s")]
+    liteserver_blacklist_window: Duration,
+
+    #[clap(long)]
+    warm_up: bool,
+    #[clap(long)]
+    read_only: bool,
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "1s")]
+    track_message_poll_interval: Duration,
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "25s")]
+    track_message_timeout: Duration,
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "1s")]
+    block_subscription_poll_interval: Duration,
+    #[clap(long, value_delimiter = ',', allow_negative_numbers = true)]
+    allowed_workchains: Vec<i32>,
+
+    /// Jetton wallet code hashes whose data is decoded from state, no default as the code differs per minter.
+    #[clap(long, value_parser = parse_code_hash)]
+    jetton_wallet_code_hash: Vec<[u8; 32]>,
+    /// NFT item code hashes whose data is decoded from state, no default as the code differs per collection.
+    #[clap(long, value_parser = parse_code_hash)]
+    nft_item_code_hash: Vec<[u8; 32]>,
+    /// Wallet code hashes whose seqno is read from state, defaults to wallet v3r1, v3r2, v4r2 and v5r1.
+    #[clap(long, value_parser = parse_code_hash)]
+    wallet_code_hash: Vec<[u8; 32]>
+}
+
+fn parse_method_value(value: &str) -> anyhow::Result<(String, f64)> {
+    let (method, value) = value.split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("expected <method>=<value>, e.g. /ton.BlockService/GetTransactions=5"))?;
+
+    Ok((method.to_owned(), parse_positive(value)?))
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn parse_positive(value: &str) -> anyhow::Result<f64> {
+    let value: f64 = value.parse()?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(anyhow::anyhow!("expected a positive number, got {}", value));
+    }
+
+    Ok(value)
+}
+
+fn parse_code_hash(value: &str) -> anyhow::Result<[u8; 32]> {
+    let bytes = STANDARD.decode(value)?;
+
+    <[u8; 32]>::try_from(bytes).map_err(|_| anyhow::anyhow!("code hash must be 32 bytes"))
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum MetricsBackend {
+    Prometheus,
+    Statsd
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd
+}
+
+impl Compression {
+    fn encoding(self) -> Option<CompressionEncoding> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some(CompressionEncoding::Gzip),
+            Self::Zstd => Some(CompressionEncoding::Zstd)
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
+    if let Some(path) = args.dump_descriptors {
+        std::fs::write(&path, ton::FILE_DESCRIPTOR_SET)?;
+
+        return Ok(());
+    }
+
+    let runtime = match args.worker_threads {
+        Some(worker_threads) => tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(worker_threads.get())
+            .enable_all()
+            .build()?,
+        None => tokio::runtime::Runtime::new()?
+    };
+
+    runtime.block_on(run(args))
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .with_span_events(FmtSpan::CLOSE)
         .init();
 
+    if args.debug_trace {
+        tracing::info!("Debug trace enabled, payloads are truncated to {} bytes", args.debug_trace_max_size);
+        tonlibjson_client::debug_trace::enable(args.debug_trace_max_size);
+    }
+
+    if let Some(worker_threads) = args.worker_threads {
+        tracing::info!("Running with {} worker threads", worker_threads);
+    }
+
     if args.enable_metrics {
-        PrometheusBuilder::new()
-            .with_http_listener(args.metrics_listen)
-            .install()
-            .expect("failed to install Prometheus recorder");
+        match args.metrics_backend {
+            MetricsBackend::Prometheus => {
+                PrometheusBuilder::new()
+                    .with_http_listener(args.metrics_listen)
+                    .set_buckets_for_metric(Matcher::Suffix(metric::REQUEST_DURATION_SUFFIX.to_owned()), &args.metrics_buckets)?
+                    .install()
+                    .expect("failed to install Prometheus recorder");
 
-        tracing::info!("Listening metrics on {:?}", &args.metrics_listen);
+                tracing::info!("Listening metrics on {:?}", &args.metrics_listen);
+            },
+            MetricsBackend::Statsd => {
+                let recorder = StatsdBuilder::from(args.statsd_host.as_str(), args.statsd_port)
+                    .histogram_is_distribution()
+                    .build(None)?;
+                metrics::set_global_recorder(recorder)
+                    .expect("failed to install StatsD recorder");
+
+                tracing::info!("Sending metrics to {}:{}", &args.statsd_host, args.statsd_port);
+            }
+        }
     }
+    server::record_build_info();
 
     tracing::info!("TON Config URL: {}", &args.ton_config_url);
 
-    let mut client = TonClientBuilder::from_config_url(args.ton_config_url, Duration::from_secs(60)).set_timeout(args.ton_timeout)
+    let mut builder = TonClientBuilder::from_config_url(args.ton_config_url, Duration::from_secs(60)).set_timeout(args.ton_timeout)
         .set_retry_budget_ttl(args.retry_budget_ttl)
         .set_retry_min_per_sec(args.retry_min_rps)
         .set_retry_percent(args.retry_withdraw_percent)
         .set_retry_first_delay(args.retry_first_delay)
         .set_retry_max_delay(args.retry_max_delay)
+        .set_retry_backoff(args.retry_backoff)
         .set_ewma_default_rtt(args.ewma_default_rtt)
-        .set_ewma_decay(args.ewma_decay)
-        .await?;
+        .set_ewma_decay(args.ewma_decay);
+    if let Some(url) = args.archival_ton_config_url.clone() {
+        tracing::info!("Archival TON Config URL: {}", &url);
+        builder = builder.set_archival_config_url(url);
+    }
+    if let Some(ttl) = args.account_state_cache_ttl {
+        tracing::info!("Account state cache enabled with ttl {:?}", ttl);
+        builder = builder.set_account_state_cache(ttl, args.account_state_cache_capacity);
+    }
+    if let Some(ttl) = args.config_cache_ttl {
+        tracing::info!("Config cache enabled with ttl {:?}", ttl);
+        builder = builder.set_config_cache(ttl, args.config_cache_capacity);
+    }
+    if let Some(ttl) = args.get_method_cache_ttl {
+        tracing::info!("Get method cache enabled with ttl {:?}", ttl);
+        builder = builder.set_get_method_cache(ttl, args.get_method_cache_capacity);
+    }
+    if let Some(ttl) = args.shards_cache_ttl {
+        tracing::info!("Shards cache enabled with ttl {:?}", ttl);
+        builder = builder.set_shards_cache(ttl, args.shards_cache_capacity);
+    }
+    if let Some(ttl) = args.send_message_dedup_ttl {
+        tracing::info!("Send message deduplication enabled with ttl {:?}", ttl);
+        builder = builder.set_send_message_dedup(ttl, args.send_message_dedup_capacity);
+    }
+    if let Some(ttl) = args.idempotency_key_ttl {
+        tracing::info!("Idempotency keys enabled with ttl {:?}", ttl);
+        builder = builder.set_idempotency_keys(ttl, args.idempotency_key_capacity);
+    }
+    if let Some(ttl) = args.masterchain_info_cache_ttl {
+        tracing::info!("Masterchain info cache enabled with ttl {:?}", ttl);
+        builder = builder.set_masterchain_info_cache(ttl);
+    }
+    if let Some(failures) = args.liteserver_blacklist_failures {
+        tracing::info!("Liteserver blacklist enabled after {} failures within {:?}", failures, args.liteserver_blacklist_window);
+        builder = builder.set_liteserver_blacklist(failures, args.liteserver_blacklist_window);
+    }
+    if args.warm_up {
+        tracing::info!("Liteserver warm-up enabled");
+        builder = builder.warm_up();
+    }
+
+    let mut client = builder.await?;
 
     client.ready().await?;
     tracing::info!("Ton Client is ready");
 
-    let reflection = tonic_reflection::server::Builder::configure()
-        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
-        .register_encoded_file_descriptor_set(ton::FILE_DESCRIPTOR_SET)
-        .build()?;
-
-    let account_service = AccountServiceServer::new(AccountService::new(client.clone()))
-        .accept_compressed(Gzip)
-        .send_compressed(Gzip);
-    let block_service = BlockServiceServer::new(BlockService::new(client.clone()))
-        .accept_compressed(Gzip)
-        .send_compressed(Gzip);
-    let message_service = MessageServiceServer::new(MessageService::new(client))
-        .accept_compressed(Gzip)
-        .send_compressed(Gzip);
+    #[cfg(unix)]
+    if let Some(path) = args.retry_config.clone() {
+        match client.retry_handle() {
+            Some(handle) => {
+                tracing::info!("Retry config will be reloaded from {:?} on SIGHUP", &path);
+                tokio::spawn(reload::reload_retry_config_on_sighup(path, handle));
+            },
+            None => tracing::warn!("--retry-config is ignored because retries are disabled")
+        }
+    }
+    #[cfg(not(unix))]
+    if args.retry_config.is_some() {
+        tracing::warn!("--retry-config is ignored because SIGHUP is only available on unix");
+    }
+
+    let reflection = if args.disable_reflection {
+        tracing::info!("Reflection disabled");
+
+        None
+    } else {
+        Some(tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+            .register_encoded_file_descriptor_set(ton::FILE_DESCRIPTOR_SET)
+            .build()?)
+    };
+
+    let synced = SyncedLayer::new(client.clone());
+    let request_duration = RequestDurationLayer::new(
+        [metric::methods(ton::FILE_DESCRIPTOR_SET)?, metric::methods(tonic_health::pb::FILE_DESCRIPTOR_SET)?].into_iter()
+            .flatten()
+            .chain(reflection::METHODS.map(str::to_owned))
+    );
+    let rate_limit = RateLimitLayer::new(
+        args.rate_limit_rps.map(|rps| TokenBucket::new(rps, args.rate_limit_burst.unwrap_or(rps))),
+        args.method_rate_limit.iter().map(|(method, rps)| (method.clone(), TokenBucket::new(*rps, *rps))).collect(),
+        args.method_weight.iter().cloned().collect()
+    );
+    let mut drain = Drain::default().set_shutdown_timeout(args.shutdown_timeout);
+    if let Some(limit) = args.max_streams_per_connection {
+        tracing::info!("Streams are limited to {} per client", limit);
+        drain = drain.set_stream_limit(limit.get());
+    }
+    let code_hashes = Arc::new(KnownCodeHashes {
+        jetton_wallet: args.jetton_wallet_code_hash.clone(),
+        nft_item: args.nft_item_code_hash.clone(),
+        wallet: match args.wallet_code_hash.as_slice() {
+            [] => WalletVersion::ALL.iter().map(WalletVersion::code_hash).collect(),
+            hashes => hashes.to_vec()
+        }
+    });
+    let workchains = match args.allowed_workchains.as_slice() {
+        [] => AllowedWorkchains::default(),
+        workchains => {
+            tracing::info!("Workchains are limited to {:?}", workchains);
+            AllowedWorkchains::new(workchains.to_vec())
+        }
+    };
+    // requests are accepted in any supported encoding, --compression only selects the one of responses
+    let mut account_service = AccountServiceServer::new(AccountService::new(client.clone(), drain.clone(), code_hashes, workchains.clone()))
+        .max_decoding_message_size(args.max_request_bytes)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+    let mut block_service = BlockServiceServer::new(BlockService::new(client.clone(), drain.clone(), workchains, args.block_subscription_poll_interval))
+        .max_decoding_message_size(args.max_request_bytes)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+    if args.read_only {
+        tracing::info!("Read-only mode enabled, SendMessage is disabled");
+    }
+    let mut message_service = MessageServiceServer::new(MessageService::new(client.clone(), args.read_only, args.track_message_poll_interval, args.track_message_timeout))
+        .max_decoding_message_size(args.max_request_bytes)
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd);
+    if let Some(encoding) = args.compression.encoding() {
+        account_service = account_service.send_compressed(encoding);
+        block_service = block_service.send_compressed(encoding);
+        message_service = message_service.send_compressed(encoding);
+    }
+
+    let features = [
+        args.archival_ton_config_url.as_ref().map(|_| "archival".to_owned()),
+        args.read_only.then(|| "read_only".to_owned()),
+        args.admin_api_key.as_ref().map(|_| "admin".to_owned())
+    ].into_iter()
+        .flatten()
+        .chain(client.cache_stats().into_iter().map(|cache| format!("cache:{}", cache.name)))
+        .collect();
+    let server_service = ServerServiceServer::new(ServerService::new(features)?);
+    let admin_service = args.admin_api_key.clone().map(|api_key| {
+        tracing::info!("Admin service enabled");
+
+        AdminServiceServer::with_interceptor(AdminService::new(client.clone()), check_api_key(api_key))
+    });
 
     let (mut health_reporter, health_server) = tonic_health::server::health_reporter();
     health_reporter.set_serving::<AccountServiceServer<AccountService>>().await;
     health_reporter.set_serving::<BlockServiceServer<BlockService>>().await;
     health_reporter.set_serving::<MessageServiceServer<MessageService>>().await;
+    health_reporter.set_serving::<ServerServiceServer<ServerService>>().await;
 
-    tracing::info!("Listening on {:?}", &args.listen);
+    let mut server = Server::builder();
+    if let (Some(cert), Some(key)) = (&args.tls_cert, &args.tls_key) {
+        let cert = std::fs::read(cert).with_context(|| format!("cannot read tls cert {:?}", cert))?;
+        let key = std::fs::read(key).with_context(|| format!("cannot read tls key {:?}", key))?;
 
-    Server::builder()
+        let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+        if let Some(ca) = &args.tls_client_ca {
+            let ca = std::fs::read(ca).with_context(|| format!("cannot read tls client ca {:?}", ca))?;
+            tls = tls.client_ca_root(Certificate::from_pem(ca));
+            tracing::info!("TLS client authentication enabled");
+        }
+
+        server = server.tls_config(tls)
+            .context("invalid tls cert, key or client ca")?;
+        tracing::info!("TLS enabled");
+    }
+
+    let router = server
         .timeout(args.timeout)
-        .tcp_keepalive(args.tcp_keepalive.into())
         .http2_keepalive_interval(args.http2_keepalive_interval.into())
         .http2_keepalive_timeout(args.http2_keepalive_timeout.into())
         .initial_connection_window_size(args.initial_connection_window_size)
         .initial_stream_window_size(args.initial_stream_window_size)
+        .layer(CatchPanicLayer)
+        .layer(ClientIdentityLayer)
+        .layer(SlowRequestLayer::new(args.slow_request_threshold))
+        .layer(request_duration)
+        .layer(rate_limit)
+        .layer(synced)
+        .layer(LiteserverLayer)
+        .layer(ReflectionV1Layer)
 
-        .add_service(reflection)
+        .add_optional_service(reflection)
         .add_service(health_server)
         .add_service(account_service)
         .add_service(block_service)
         .add_service(message_service)
+        .add_service(server_service)
+        .add_optional_service(admin_service);
+
+    let shutdown = {
+        let drain = drain.clone();
+
+        async move {
+            ton_client_utils::shutdown::signal().await;
 
-        .serve_with_shutdown(args.listen, async move { tokio::signal::ctrl_c().await.unwrap(); })
-        .await?;
+            tracing::info!("Shutting down, draining {} streams", drain.active());
+            drain.drain();
+        }
+    };
+
+    if args.proxy_protocol {
+        tracing::info!("PROXY protocol enabled");
+    }
+    let incoming = listener::incoming(&args.listen, args.uds.as_deref(), args.proxy_protocol, args.tcp_keepalive)?;
+    let server = router.serve_with_incoming_shutdown(incoming, shutdown);
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => result?,
+        _ = async { drain.draining().await; tokio::time::sleep(args.shutdown_timeout + FORCE_CLOSE_GRACE).await; } => {
+            tracing::warn!("Shutdown timeout of {:?} exceeded", args.shutdown_timeout);
+        }
+    }
+
+    tracing::info!("Drained {} streams, force-closed {} streams", drain.drained(), drain.force_closed() + drain.unfinished());
+    if let Some(path) = &args.uds {
+        let _ = std::fs::remove_file(path);
+    }
 
     Ok(())
 }
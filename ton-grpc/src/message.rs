@@ -1,25 +1,257 @@
 #![allow(clippy::blocks_in_conditions)]
 
+use std::str::FromStr;
+use std::time::Duration;
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE, URL_SAFE_NO_PAD}, DecodeError, Engine};
 use tonic::{async_trait, Request, Response, Status};
-use tonlibjson_client::ton::TonClient;
+use toner::tlb::bits::de::unpack_bytes;
+use toner::tlb::ser::CellSerializeExt;
+use toner::ton::boc::BoC;
+use toner::ton::message::{CommonMsgInfo, Message};
+use toner::ton::MsgAddress;
+use tonlibjson_client::address::AccountAddressData;
+use tonlibjson_client::ton::{IdempotencyKeyReused, TonClient};
 use derive_new::new;
+use crate::client_identity::ClientIdentity;
+use crate::error;
+use crate::helpers::address;
+use crate::trace;
 use crate::ton::message_service_server::MessageService as BaseMessageService;
-use crate::ton::{SendRequest, SendResponse};
+use crate::ton::{EstimateFeeRequest, EstimateFeeResponse, SendRequest, SendResponse, TrackMessageRequest, Transaction, TryLocateTxRequest, ValidateMessageResponse};
+
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
 
 #[derive(new)]
 pub struct MessageService {
-    client: TonClient
+    client: TonClient,
+    read_only: bool,
+    track_poll_interval: Duration,
+    track_timeout: Duration
 }
 
 #[async_trait]
 impl BaseMessageService for MessageService {
     #[tracing::instrument(skip_all, err)]
     async fn send_message(&self, request: Request<SendRequest>) -> Result<Response<SendResponse>, Status> {
-        let msg = request.into_inner();
+        if self.read_only {
+            return Err(Status::permission_denied("read-only mode, sending messages is disabled"));
+        }
+        if let Some(identity) = request.extensions().get::<ClientIdentity>() {
+            tracing::info!(client = %identity.common_name, "sending message");
+        }
+        let idempotency_key = request.metadata()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .map(|value| value.to_str().map(ToOwned::to_owned))
+            .transpose()
+            .map_err(|_| error::invalid_argument(IDEMPOTENCY_KEY_HEADER, format!("{} must be ascii", IDEMPOTENCY_KEY_HEADER)))?;
+        let msg = trace::request(request.into_inner());
+        let body = normalize_boc(&msg.body)?;
+
+        let hash = match idempotency_key {
+            Some(key) => self.client.send_message_with_idempotency_key(&key, &body).await,
+            None => self.client.send_message_returning_hash(&body).await
+        }.map_err(|e| {
+            if e.is::<IdempotencyKeyReused>() {
+                return Status::failed_precondition(e.to_string());
+            }
+
+            error::internal(e)
+        })?;
+
+        Ok(Response::new(trace::response(SendResponse { hash })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn validate_message(&self, request: Request<SendRequest>) -> Result<Response<ValidateMessageResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+
+        let mut problems = Vec::new();
+        match parse_external_message(&msg.body) {
+            Err(problem) => problems.push(problem),
+            Ok(message) => {
+                let state = self.client.raw_get_account_state(&message.destination.to_base64_std()).await
+                    .map_err(error::internal)?;
+
+                if state.code.is_empty() {
+                    match message.init_hash {
+                        None if !state.frozen_hash.is_empty() => problems.push("destination account is frozen and message has no state init".to_owned()),
+                        None => problems.push("destination account is not deployed and message has no state init".to_owned()),
+                        Some(hash) if state.frozen_hash.is_empty() && hash != message.destination.address => {
+                            problems.push("state init does not match destination address".to_owned())
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(Response::new(trace::response(ValidateMessageResponse { valid: problems.is_empty(), problems })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn track_message(&self, request: Request<TrackMessageRequest>) -> Result<Response<Transaction>, Status> {
+        let msg = trace::request(request.into_inner());
+        if self.read_only && !msg.already_sent {
+            return Err(Status::permission_denied("read-only mode, sending messages is disabled"));
+        }
+
+        let body = normalize_boc(&msg.body)?;
+        let message = parse_external_message(&body)
+            .map_err(|e| error::invalid_argument("body", e))?;
+        let address = message.destination.to_base64_std();
+        let body_hash = STANDARD.encode(message.body_hash);
+
+        let since = if msg.already_sent { None } else {
+            let state = self.client.without_cache().raw_get_account_state(&address).await
+                .map_err(error::internal)?;
+            self.client.send_message(&body).await
+                .map_err(error::internal)?;
+
+            state.last_transaction_id
+        };
+
+        let tx = tokio::time::timeout(self.track_timeout, self.client.wait_for_transaction(&address, &body_hash, since, self.track_poll_interval)).await
+            .map_err(|_| Status::deadline_exceeded("transaction did not appear before the tracking timeout"))?
+            .map_err(error::internal)?;
+
+        let address = AccountAddressData::from_str(&address)
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response((&address, tx).into())))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn estimate_fee(&self, request: Request<EstimateFeeRequest>) -> Result<Response<EstimateFeeResponse>, Status> {
+        let msg = trace::request(request.into_inner());
+        let address = address::parse(&msg.account_address)
+            .map_err(|e| error::invalid_argument("account_address", e))?;
+
+        let body = normalize_boc(&msg.body)?;
+
+        let fees = self.client.estimate_fee(&address.to_raw_string(), &body, &msg.init_code, &msg.init_data, msg.ignore_chksig.unwrap_or(true)).await
+            .map_err(error::internal)?;
+
+        Ok(Response::new(trace::response(EstimateFeeResponse {
+            source_fees: Some(fees.source_fees.into()),
+            destination_fees: fees.destination_fees.into_iter().map(Into::into).collect()
+        })))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn try_locate_source_tx(&self, request: Request<TryLocateTxRequest>) -> Result<Response<Transaction>, Status> {
+        let msg = trace::request(request.into_inner());
+        let (source, destination) = parse_locate_request(&msg)?;
+
+        let tx = self.client.try_locate_source_tx(&source.to_raw_string(), &destination.to_raw_string(), msg.created_lt).await
+            .map_err(error::internal)?
+            .ok_or_else(|| Status::not_found("source transaction not found"))?;
+
+        Ok(Response::new(trace::response((&source, tx).into())))
+    }
+
+    #[tracing::instrument(skip_all, err)]
+    async fn try_locate_result_tx(&self, request: Request<TryLocateTxRequest>) -> Result<Response<Transaction>, Status> {
+        let msg = trace::request(request.into_inner());
+        let (source, destination) = parse_locate_request(&msg)?;
+
+        let tx = self.client.try_locate_result_tx(&source.to_raw_string(), &destination.to_raw_string(), msg.created_lt).await
+            .map_err(error::internal)?
+            .ok_or_else(|| Status::not_found("result transaction not found"))?;
+
+        Ok(Response::new(trace::response((&destination, tx).into())))
+    }
+}
+
+fn parse_locate_request(msg: &TryLocateTxRequest) -> Result<(AccountAddressData, AccountAddressData), Status> {
+    let source = address::parse(&msg.source)
+        .map_err(|e| error::invalid_argument("source", e))?;
+    let destination = address::parse(&msg.destination)
+        .map_err(|e| error::invalid_argument("destination", e))?;
+    if msg.created_lt <= 0 {
+        return Err(error::invalid_argument("created_lt", "created_lt must be greater than 0"));
+    }
+
+    Ok((source, destination))
+}
+
+struct ExternalMessage {
+    destination: MsgAddress,
+    init_hash: Option<[u8; 32]>,
+    body_hash: [u8; 32]
+}
+
+/// Wallets often produce url-safe base64, try it after the standard alphabet and report the standard error.
+fn decode_boc(body: &str) -> Result<Vec<u8>, DecodeError> {
+    STANDARD.decode(body)
+        .or_else(|e| URL_SAFE.decode(body).or_else(|_| URL_SAFE_NO_PAD.decode(body)).map_err(|_| e))
+}
+
+/// Tonlib only accepts standard base64.
+fn normalize_boc(body: &str) -> Result<String, Status> {
+    decode_boc(body)
+        .map(|bytes| STANDARD.encode(bytes))
+        .map_err(|e| error::invalid_argument("body", format!("invalid base64: {}", e)))
+}
+
+fn parse_external_message(body: &str) -> Result<ExternalMessage, String> {
+    let bytes = decode_boc(body)
+        .map_err(|e| format!("invalid base64: {}", e))?;
+    let boc: BoC = unpack_bytes(bytes)
+        .map_err(|e| format!("invalid boc: {}", e))?;
+    let root = boc.single_root()
+        .ok_or_else(|| "boc must contain a single root cell".to_owned())?;
+    let message: Message = root.parse_fully()
+        .map_err(|e| format!("invalid message: {}", e))?;
+
+    let CommonMsgInfo::ExternalIn(info) = message.info else {
+        return Err("message is not an external inbound message".to_owned());
+    };
+    let init_hash = message.init
+        .map(|init| init.to_cell().map(|cell| cell.hash()))
+        .transpose()
+        .map_err(|e| format!("invalid state init: {}", e))?;
+
+    Ok(ExternalMessage { destination: info.dst, init_hash, body_hash: message.body.hash() })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::{decode_boc, normalize_boc, parse_external_message};
+
+    #[test]
+    fn decode_boc_accepts_url_safe_alphabet() {
+        assert_eq!(decode_boc("+/8=").unwrap(), vec![0xfb, 0xff]);
+        assert_eq!(decode_boc("-_8=").unwrap(), vec![0xfb, 0xff]);
+        assert_eq!(decode_boc("-_8").unwrap(), vec![0xfb, 0xff]);
+    }
+
+    #[test]
+    fn normalize_boc_reencodes_as_standard() {
+        assert_eq!(normalize_boc("-_8").unwrap(), "+/8=");
+        assert!(normalize_boc("not base64!").is_err());
+    }
+
+    #[test]
+    fn parse_external_message_reads_destination_and_state_init() {
+        // ext_in_msg_info to 0:1111...11 with an empty state init in a reference and an empty body
+        let message = parse_external_message("te6ccgEBAgEAKQABRYgAIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIaAQABBA==").unwrap();
+
+        assert_eq!(message.destination.workchain_id, 0);
+        assert_eq!(message.destination.address, [0x11; 32]);
+        assert_eq!(message.init_hash.map(hex::encode).as_deref(), Some("3f078d3b7e22c8944e5561909a236ae48b48a7ea42f28dd861c22b6f64d7e97b"));
+    }
+
+    #[test]
+    fn parse_external_message_rejects_invalid_base64() {
+        let problem = parse_external_message("not base64!").err().unwrap();
+
+        assert!(problem.starts_with("invalid base64"));
+    }
 
-        let hash = self.client.send_message_returning_hash(&msg.body).await
-            .map_err(|e| Status::internal(e.to_string()))?;
+    #[test]
+    fn parse_external_message_rejects_invalid_boc() {
+        let problem = parse_external_message("AAAA").err().unwrap();
 
-        Ok(Response::new(SendResponse { hash }))
+        assert!(problem.starts_with("invalid boc"));
     }
 }
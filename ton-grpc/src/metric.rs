@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use prost::Message;
+use prost_types::FileDescriptorSet;
+use tonic::body::BoxBody;
+use tonic::codegen::http::{Request, Response};
+use tower::{Layer, Service};
+
+pub const REQUEST_DURATION_SUFFIX: &str = "_duration_seconds";
+const UNKNOWN_METHOD: &str = "unknown";
+
+/// Labels requests with their method when it is one of `methods`, any other path a client sends is labeled
+/// `unknown` so it doesn't create a new series.
+#[derive(Clone)]
+pub struct RequestDurationLayer {
+    methods: Arc<HashSet<String>>
+}
+
+impl RequestDurationLayer {
+    pub fn new(methods: impl IntoIterator<Item = String>) -> Self {
+        Self { methods: Arc::new(methods.into_iter().collect()) }
+    }
+}
+
+impl<S> Layer<S> for RequestDurationLayer {
+    type Service = RequestDurationService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestDurationService { inner, methods: self.methods.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestDurationService<S> {
+    inner: S,
+    methods: Arc<HashSet<String>>
+}
+
+/// Paths of every method of the services in the encoded `descriptor` set.
+pub fn methods(descriptor: &[u8]) -> anyhow::Result<Vec<String>> {
+    let descriptor = FileDescriptorSet::decode(descriptor)?;
+
+    Ok(descriptor.file.iter()
+        .flat_map(|file| file.service.iter().map(move |service| (file.package(), service)))
+        .flat_map(|(package, service)| service.method.iter()
+            .map(move |method| format!("/{}.{}/{}", package, service.name(), method.name())))
+        .collect())
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for RequestDurationService<S>
+    where S: Service<Request<ReqBody>, Response = Response<BoxBody>>,
+          S::Future: Send + 'static,
+          S::Error: Send + 'static {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let method = match self.methods.get(req.uri().path()) {
+            Some(method) => method.clone(),
+            None => UNKNOWN_METHOD.to_owned()
+        };
+        let started_at = Instant::now();
+        let future = self.inner.call(req);
+
+        async move {
+            let response = future.await;
+
+            metrics::histogram!("ton_grpc_request_duration_seconds", "method" => method).record(started_at.elapsed());
+
+            response
+        }.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metric::methods;
+    use crate::ton::FILE_DESCRIPTOR_SET;
+
+    #[test]
+    fn methods_lists_service_routes() {
+        let methods = methods(FILE_DESCRIPTOR_SET).unwrap();
+
+        assert!(methods.contains(&"/ton.BlockService/GetLastBlock".to_owned()));
+        assert!(methods.iter().all(|method| method.starts_with("/ton.")));
+    }
+}
@@ -0,0 +1,69 @@
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::task::{Context, Poll};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tonic::body::BoxBody;
+use tonic::codegen::http::{Request, Response};
+use tonic::Status;
+use tower::{Layer, Service};
+
+#[derive(Clone, Default)]
+pub struct CatchPanicLayer;
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanicService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        metrics::describe_counter!("ton_grpc_panic_count", "Count of panics caught in gRPC handlers");
+
+        CatchPanicService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct CatchPanicService<S> {
+    inner: S
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for CatchPanicService<S>
+    where S: Service<Request<ReqBody>, Response = Response<BoxBody>>,
+          S::Future: Send + 'static,
+          S::Error: Send + 'static {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let method = req.uri().path().to_owned();
+        let future = std::panic::catch_unwind(AssertUnwindSafe(|| self.inner.call(req)));
+
+        async move {
+            let result = match future {
+                Ok(future) => AssertUnwindSafe(future).catch_unwind().await,
+                Err(panic) => Err(panic)
+            };
+
+            result.unwrap_or_else(|panic| {
+                tracing::error!(method = method, panic = panic_message(&*panic), "handler panicked");
+                metrics::counter!("ton_grpc_panic_count", "method" => method).increment(1);
+
+                Ok(Status::internal("internal error").to_http())
+            })
+        }.boxed()
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
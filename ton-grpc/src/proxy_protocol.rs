@@ -0,0 +1,173 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Context, Result};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LENGTH: usize = 107;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Header {
+    Proxied { source: SocketAddr, destination: SocketAddr },
+    Local
+}
+
+pub async fn read_header<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Header> {
+    let mut prefix = [0; 12];
+    reader.read_exact(&mut prefix).await?;
+
+    if prefix == V2_SIGNATURE {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header).await?;
+
+        let mut payload = vec![0; u16::from_be_bytes([header[2], header[3]]) as usize];
+        reader.read_exact(&mut payload).await?;
+
+        return parse_v2(header[0], header[1], &payload);
+    }
+
+    if !prefix.starts_with(V1_PREFIX) {
+        bail!("PROXY protocol header is missing");
+    }
+
+    let mut line = prefix.to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LENGTH {
+            bail!("PROXY protocol v1 header is too long");
+        }
+
+        line.push(reader.read_u8().await?);
+    }
+
+    parse_v1(std::str::from_utf8(&line)?)
+}
+
+fn parse_v1(line: &str) -> Result<Header> {
+    let mut parts = line.trim_end_matches("\r\n").split(' ').skip(1);
+
+    match parts.next() {
+        Some("TCP4") | Some("TCP6") => {},
+        Some("UNKNOWN") => return Ok(Header::Local),
+        _ => bail!("unsupported PROXY protocol v1 family")
+    }
+
+    let mut next = || parts.next().context("truncated PROXY protocol v1 header");
+    let source_ip = IpAddr::from_str(next()?)?;
+    let destination_ip = IpAddr::from_str(next()?)?;
+    let source_port = u16::from_str(next()?)?;
+    let destination_port = u16::from_str(next()?)?;
+
+    Ok(Header::Proxied {
+        source: SocketAddr::new(source_ip, source_port),
+        destination: SocketAddr::new(destination_ip, destination_port)
+    })
+}
+
+fn parse_v2(version_command: u8, family: u8, payload: &[u8]) -> Result<Header> {
+    if version_command >> 4 != 2 {
+        bail!("unsupported PROXY protocol version");
+    }
+
+    match version_command & 0x0F {
+        0x00 => return Ok(Header::Local),
+        0x01 => {},
+        _ => bail!("unsupported PROXY protocol v2 command")
+    }
+
+    let port = |offset: usize| u16::from_be_bytes([payload[offset], payload[offset + 1]]);
+    match family >> 4 {
+        0x01 if payload.len() >= 12 => {
+            let source = Ipv4Addr::from(<[u8; 4]>::try_from(&payload[0..4])?);
+            let destination = Ipv4Addr::from(<[u8; 4]>::try_from(&payload[4..8])?);
+
+            Ok(Header::Proxied {
+                source: SocketAddr::new(source.into(), port(8)),
+                destination: SocketAddr::new(destination.into(), port(10))
+            })
+        },
+        0x02 if payload.len() >= 36 => {
+            let source = Ipv6Addr::from(<[u8; 16]>::try_from(&payload[0..16])?);
+            let destination = Ipv6Addr::from(<[u8; 16]>::try_from(&payload[16..32])?);
+
+            Ok(Header::Proxied {
+                source: SocketAddr::new(source.into(), port(32)),
+                destination: SocketAddr::new(destination.into(), port(34))
+            })
+        },
+        0x01 | 0x02 => Err(anyhow!("truncated PROXY protocol v2 header")),
+        _ => Ok(Header::Local)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::proxy_protocol::{Header, read_header};
+
+    #[tokio::test]
+    async fn parses_v1_tcp4() {
+        let mut input: &[u8] = b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\nPRI";
+
+        let header = read_header(&mut input).await.unwrap();
+
+        assert_eq!(header, Header::Proxied {
+            source: "192.168.0.1:56324".parse().unwrap(),
+            destination: "10.0.0.1:443".parse().unwrap()
+        });
+        assert_eq!(input, b"PRI");
+    }
+
+    #[tokio::test]
+    async fn parses_v1_tcp6() {
+        let mut input: &[u8] = b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n";
+
+        let header = read_header(&mut input).await.unwrap();
+
+        assert_eq!(header, Header::Proxied {
+            source: "[2001:db8::1]:56324".parse().unwrap(),
+            destination: "[2001:db8::2]:443".parse().unwrap()
+        });
+    }
+
+    #[tokio::test]
+    async fn parses_v1_unknown() {
+        let mut input: &[u8] = b"PROXY UNKNOWN\r\n";
+
+        assert_eq!(read_header(&mut input).await.unwrap(), Header::Local);
+    }
+
+    #[tokio::test]
+    async fn parses_v2_tcp4() {
+        let mut input: Vec<u8> = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+        input.extend([0x21, 0x11, 0x00, 0x0C]);
+        input.extend([192, 168, 0, 1, 10, 0, 0, 1]);
+        input.extend(56324_u16.to_be_bytes());
+        input.extend(443_u16.to_be_bytes());
+        input.extend(b"PRI");
+        let mut input = input.as_slice();
+
+        let header = read_header(&mut input).await.unwrap();
+
+        assert_eq!(header, Header::Proxied {
+            source: "192.168.0.1:56324".parse().unwrap(),
+            destination: "10.0.0.1:443".parse().unwrap()
+        });
+        assert_eq!(input, b"PRI");
+    }
+
+    #[tokio::test]
+    async fn parses_v2_local() {
+        let mut input: Vec<u8> = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+        input.extend([0x20, 0x00, 0x00, 0x00]);
+        let mut input = input.as_slice();
+
+        assert_eq!(read_header(&mut input).await.unwrap(), Header::Local);
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_header() {
+        let mut input: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+        assert!(read_header(&mut input).await.is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::time::Instant;
+use tonic::body::BoxBody;
+use tonic::codegen::http::{Request, Response};
+use tonic::metadata::MetadataValue;
+use tonic::Status;
+use tower::{Layer, Service};
+
+const RETRY_AFTER_HEADER: &str = "retry-after-ms";
+
+pub struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    state: Mutex<(f64, Instant)>
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, capacity: f64) -> Self {
+        Self { capacity, rate, state: Mutex::new((capacity, Instant::now())) }
+    }
+
+    fn try_acquire(&self, weight: f64) -> Result<(), Duration> {
+        let weight = weight.min(self.capacity);
+        let mut state = self.state.lock().unwrap();
+        let (tokens, refilled_at) = *state;
+
+        let now = Instant::now();
+        let tokens = (tokens + now.duration_since(refilled_at).as_secs_f64() * self.rate).min(self.capacity);
+        if tokens >= weight {
+            *state = (tokens - weight, now);
+
+            return Ok(());
+        }
+
+        *state = (tokens, now);
+        Err(Duration::from_secs_f64((weight - tokens) / self.rate))
+    }
+
+    fn refund(&self, weight: f64) {
+        let mut state = self.state.lock().unwrap();
+
+        state.0 = (state.0 + weight.min(self.capacity)).min(self.capacity);
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct RateLimitLayer {
+    global: Option<Arc<TokenBucket>>,
+    methods: Arc<HashMap<String, TokenBucket>>,
+    weights: Arc<HashMap<String, f64>>
+}
+
+impl RateLimitLayer {
+    pub fn new(global: Option<TokenBucket>, methods: HashMap<String, TokenBucket>, weights: HashMap<String, f64>) -> Self {
+        metrics::describe_counter!("ton_grpc_rate_limited_total", "Count of requests rejected by the rate limiter");
+
+        Self { global: global.map(Arc::new), methods: Arc::new(methods), weights: Arc::new(weights) }
+    }
+
+    fn check(&self, method: &str) -> Result<(), Duration> {
+        let bucket = self.methods.get(method);
+        if let Some(bucket) = bucket {
+            bucket.try_acquire(1.0)?;
+        }
+
+        if let Some(global) = &self.global {
+            if let Err(retry_after) = global.try_acquire(self.weights.get(method).copied().unwrap_or(1.0)) {
+                // the request is rejected anyway, so it shouldn't count against the method
+                if let Some(bucket) = bucket {
+                    bucket.refund(1.0);
+                }
+
+                return Err(retry_after);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService { inner, limiter: self.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: RateLimitLayer
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for RateLimitService<S>
+    where S: Service<Request<ReqBody>, Response = Response<BoxBody>>,
+          S::Future: Send + 'static,
+          S::Error: Send + 'static {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let method = req.uri().path();
+        if !method.starts_with("/ton.") {
+            return self.inner.call(req).boxed();
+        }
+
+        if let Err(retry_after) = self.limiter.check(method) {
+            metrics::counter!("ton_grpc_rate_limited_total", "method" => method.to_owned()).increment(1);
+
+            let retry_after = retry_after.as_millis().max(1);
+            let mut status = Status::resource_exhausted(format!("rate limited, retry after {}ms", retry_after));
+            status.metadata_mut().insert(RETRY_AFTER_HEADER, MetadataValue::from(retry_after as u64));
+
+            return futures::future::ready(Ok(status.to_http())).boxed();
+        }
+
+        self.inner.call(req).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use crate::rate_limit::{RateLimitLayer, TokenBucket};
+
+    #[tokio::test(start_paused = true)]
+    async fn bucket_refills_over_time() {
+        let bucket = TokenBucket::new(2.0, 2.0);
+
+        assert!(bucket.try_acquire(1.0).is_ok());
+        assert!(bucket.try_acquire(1.0).is_ok());
+        assert_eq!(bucket.try_acquire(1.0), Err(Duration::from_millis(500)));
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+
+        assert!(bucket.try_acquire(1.0).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn weighted_methods_consume_more_tokens() {
+        let weights = HashMap::from([("/ton.BlockService/GetTransactions".to_owned(), 5.0)]);
+        let limiter = RateLimitLayer::new(Some(TokenBucket::new(1.0, 5.0)), HashMap::new(), weights);
+
+        assert!(limiter.check("/ton.BlockService/GetTransactions").is_ok());
+        assert!(limiter.check("/ton.BlockService/GetLastBlock").is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn per_method_limit_is_independent() {
+        let methods = HashMap::from([("/ton.AccountService/GetAccountState".to_owned(), TokenBucket::new(1.0, 1.0))]);
+        let limiter = RateLimitLayer::new(None, methods, HashMap::new());
+
+        assert!(limiter.check("/ton.AccountService/GetAccountState").is_ok());
+        assert!(limiter.check("/ton.AccountService/GetAccountState").is_err());
+        assert!(limiter.check("/ton.BlockService/GetLastBlock").is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn global_rejection_keeps_method_token() {
+        let methods = HashMap::from([("/ton.AccountService/GetAccountState".to_owned(), TokenBucket::new(0.1, 1.0))]);
+        let limiter = RateLimitLayer::new(Some(TokenBucket::new(1.0, 1.0)), methods, HashMap::new());
+
+        assert!(limiter.check("/ton.BlockService/GetLastBlock").is_ok());
+        assert!(limiter.check("/ton.AccountService/GetAccountState").is_err());
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+
+        assert!(limiter.check("/ton.AccountService/GetAccountState").is_ok());
+    }
+}
@@ -0,0 +1,53 @@
+use std::task::{Context, Poll};
+use tonic::codegen::http::{Request, Uri};
+use tonic::codegen::http::uri::PathAndQuery;
+use tower::{Layer, Service};
+
+const V1_PREFIX: &str = "/grpc.reflection.v1.ServerReflection/";
+const V1ALPHA_PREFIX: &str = "/grpc.reflection.v1alpha.ServerReflection/";
+pub const METHODS: [&str; 2] = [
+    "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo",
+    "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo"
+];
+
+// tonic-reflection 0.11 only ships v1alpha, and v1 is the same protocol under a new package name,
+// so v1 calls are served by the v1alpha service
+#[derive(Clone, Default)]
+pub struct ReflectionV1Layer;
+
+impl<S> Layer<S> for ReflectionV1Layer {
+    type Service = ReflectionV1Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ReflectionV1Service { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct ReflectionV1Service<S> {
+    inner: S
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for ReflectionV1Service<S>
+    where S: Service<Request<ReqBody>> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        if let Some(method) = req.uri().path().strip_prefix(V1_PREFIX) {
+            let mut parts = req.uri().clone().into_parts();
+            parts.path_and_query = PathAndQuery::try_from(format!("{V1ALPHA_PREFIX}{method}")).ok();
+
+            if let Ok(uri) = Uri::from_parts(parts) {
+                *req.uri_mut() = uri;
+            }
+        }
+
+        self.inner.call(req)
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use tokio::signal::unix::{signal, SignalKind};
+use tonlibjson_client::retry::{RetryConfig, RetryHandle};
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RetryConfigFile {
+    budget_ttl: Option<String>,
+    min_rps: Option<u32>,
+    withdraw_percent: Option<f32>,
+    first_delay: Option<String>,
+    max_delay: Option<String>,
+    backoff: Option<String>
+}
+
+fn parse_duration(value: Option<String>, default: Duration) -> Result<Duration> {
+    Ok(match value {
+        Some(value) => humantime::parse_duration(&value)?,
+        None => default
+    })
+}
+
+fn apply(config: RetryConfig, contents: &str) -> Result<RetryConfig> {
+    let file: RetryConfigFile = serde_json::from_str(contents)?;
+
+    let config = RetryConfig {
+        budget_ttl: parse_duration(file.budget_ttl, config.budget_ttl)?,
+        min_per_sec: file.min_rps.unwrap_or(config.min_per_sec),
+        retry_percent: file.withdraw_percent.unwrap_or(config.retry_percent),
+        first_delay: parse_duration(file.first_delay, config.first_delay)?,
+        max_delay: parse_duration(file.max_delay, config.max_delay)?,
+        backoff: file.backoff.as_deref().map(str::parse).transpose()?.unwrap_or(config.backoff)
+    };
+
+    // the retry budget panics outside of these ranges
+    if !(Duration::from_secs(1)..=Duration::from_secs(60)).contains(&config.budget_ttl) {
+        bail!("budget_ttl must be between 1s and 60s, got {:?}", config.budget_ttl);
+    }
+    if !(0.0..=1000.0).contains(&config.retry_percent) {
+        bail!("withdraw_percent must be between 0 and 1000, got {}", config.retry_percent);
+    }
+
+    Ok(config)
+}
+
+pub async fn reload_retry_config_on_sighup(path: PathBuf, handle: RetryHandle) -> Result<()> {
+    let mut hangup = signal(SignalKind::hangup())?;
+    let initial = handle.config();
+
+    while hangup.recv().await.is_some() {
+        let config = tokio::fs::read_to_string(&path).await
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| apply(initial, &contents));
+
+        match config {
+            Ok(config) => handle.reload(config),
+            Err(e) => tracing::error!(error = ?e, path = ?path, "failed to reload retry config")
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tonlibjson_client::retry::{RetryBackoff, RetryConfig};
+    use crate::reload::apply;
+
+    fn config() -> RetryConfig {
+        RetryConfig {
+            budget_ttl: Duration::from_secs(10),
+            min_per_sec: 1,
+            retry_percent: 0.1,
+            first_delay: Duration::from_millis(128),
+            max_delay: Duration::from_millis(4096),
+            backoff: RetryBackoff::Fibonacci
+        }
+    }
+
+    #[test]
+    fn overrides_only_given_fields() {
+        let config = apply(config(), r#"{"min_rps": 5, "max_delay": "1s"}"#).unwrap();
+
+        assert_eq!(config.min_per_sec, 5);
+        assert_eq!(config.max_delay, Duration::from_secs(1));
+        assert_eq!(config.first_delay, Duration::from_millis(128));
+        assert_eq!(config.budget_ttl, Duration::from_secs(10));
+        assert_eq!(config.backoff, RetryBackoff::Fibonacci);
+    }
+
+    #[test]
+    fn overrides_backoff() {
+        let config = apply(config(), r#"{"backoff": "constant"}"#).unwrap();
+
+        assert_eq!(config.backoff, RetryBackoff::Constant);
+        assert!(apply(config, r#"{"backoff": "linear"}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_budget() {
+        assert!(apply(config(), r#"{"budget_ttl": "500ms"}"#).is_err());
+        assert!(apply(config(), r#"{"budget_ttl": "2m"}"#).is_err());
+        assert!(apply(config(), r#"{"withdraw_percent": -0.1}"#).is_err());
+        assert!(apply(config(), r#"{"withdraw_percent": 1001}"#).is_err());
+        assert!(apply(config(), r#"{"budget_ttl": "60s", "withdraw_percent": 1000}"#).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(apply(config(), r#"{"retry_min_rps": 5}"#).is_err());
+    }
+}
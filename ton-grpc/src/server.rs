@@ -0,0 +1,71 @@
+use prost::Message;
+use prost_types::FileDescriptorSet;
+use tonic::{async_trait, Request, Response, Status};
+use crate::trace;
+use crate::ton::server_service_server::ServerService as BaseServerService;
+use crate::ton::{Capabilities, GetCapabilitiesRequest, GetServerVersionRequest, ServerVersion, FILE_DESCRIPTOR_SET};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const GIT_SHA: &str = env!("GIT_SHA");
+const ADMIN_SERVICE: &str = "AdminService";
+
+pub fn record_build_info() {
+    metrics::describe_gauge!("ton_build_info", "Build information of the running server");
+    metrics::gauge!(
+        "ton_build_info",
+        "version" => VERSION,
+        "git_sha" => GIT_SHA,
+        "tonlib_version" => tonlibjson_client::TONLIB_VERSION
+    ).set(1.);
+}
+
+pub struct ServerService {
+    capabilities: Capabilities
+}
+
+impl ServerService {
+    pub fn new(features: Vec<String>) -> anyhow::Result<Self> {
+        let admin_enabled = features.iter().any(|feature| feature == "admin");
+        let descriptor = FileDescriptorSet::decode(FILE_DESCRIPTOR_SET)?;
+        let methods = descriptor.file.iter()
+            .flat_map(|file| file.service.iter().map(move |service| (file.package(), service)))
+            .filter(|(_, service)| admin_enabled || service.name() != ADMIN_SERVICE)
+            .flat_map(|(package, service)| service.method.iter()
+                .map(move |method| format!("/{}.{}/{}", package, service.name(), method.name())))
+            .collect();
+
+        Ok(Self { capabilities: Capabilities { methods, features } })
+    }
+}
+
+#[async_trait]
+impl BaseServerService for ServerService {
+    async fn get_server_version(&self, _request: Request<GetServerVersionRequest>) -> Result<Response<ServerVersion>, Status> {
+        Ok(Response::new(trace::response(ServerVersion {
+            version: VERSION.to_owned(),
+            git_sha: GIT_SHA.to_owned(),
+            tonlib_version: tonlibjson_client::TONLIB_VERSION.to_owned()
+        })))
+    }
+
+    async fn get_capabilities(&self, _request: Request<GetCapabilitiesRequest>) -> Result<Response<Capabilities>, Status> {
+        Ok(Response::new(trace::response(self.capabilities.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::ServerService;
+
+    #[test]
+    fn capabilities_hide_admin_service_unless_enabled() {
+        let service = ServerService::new(vec![]).unwrap();
+
+        assert!(service.capabilities.methods.contains(&"/ton.ServerService/GetCapabilities".to_owned()));
+        assert!(!service.capabilities.methods.iter().any(|method| method.starts_with("/ton.AdminService/")));
+
+        let service = ServerService::new(vec!["admin".to_owned()]).unwrap();
+
+        assert!(service.capabilities.methods.contains(&"/ton.AdminService/FlushCache".to_owned()));
+    }
+}
@@ -0,0 +1,73 @@
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use derive_new::new;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tonic::body::BoxBody;
+use tonic::codegen::http::{Request, Response};
+use tower::{Layer, Service};
+use tonlibjson_client::liteserver::LiteserverScope;
+
+#[derive(new, Clone)]
+pub struct SlowRequestLayer {
+    threshold: Option<Duration>
+}
+
+impl<S> Layer<S> for SlowRequestLayer {
+    type Service = SlowRequestService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowRequestService { inner, threshold: self.threshold }
+    }
+}
+
+#[derive(Clone)]
+pub struct SlowRequestService<S> {
+    inner: S,
+    threshold: Option<Duration>
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for SlowRequestService<S>
+    where S: Service<Request<ReqBody>, Response = Response<BoxBody>>,
+          S::Future: Send + 'static,
+          S::Error: Send + 'static {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let Some(threshold) = self.threshold else {
+            return self.inner.call(req).boxed();
+        };
+
+        let method = req.uri().path().to_owned();
+        let user_agent = req.headers()
+            .get("user-agent")
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned);
+        let started_at = Instant::now();
+        let future = self.inner.call(req);
+
+        async move {
+            let response = future.await?;
+
+            let elapsed = started_at.elapsed();
+            if elapsed > threshold {
+                let liteserver = response.extensions()
+                    .get::<LiteserverScope>()
+                    .and_then(LiteserverScope::served_by);
+                let grpc_status = response.headers()
+                    .get("grpc-status")
+                    .and_then(|value| value.to_str().ok());
+
+                tracing::warn!(method = method, elapsed = ?elapsed, liteserver = liteserver.as_deref(), user_agent = user_agent, grpc_status = grpc_status, "slow request");
+            }
+
+            Ok(response)
+        }.boxed()
+    }
+}
@@ -0,0 +1,61 @@
+use std::future::ready;
+use std::task::{Context, Poll};
+use derive_new::new;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tonic::body::BoxBody;
+use tonic::codegen::http::{Request, Response};
+use tower::{Layer, Service};
+use tonlibjson_client::ton::TonClient;
+use crate::error;
+
+const GATED_PREFIX: &str = "/ton.";
+const UNGATED_PATHS: [&str; 6] = [
+    "/ton.BlockService/GetSyncStatus",
+    "/ton.ServerService/GetServerVersion",
+    "/ton.ServerService/GetCapabilities",
+    "/ton.AccountService/ValidateAddress",
+    "/ton.AdminService/GetCacheStats",
+    "/ton.AdminService/FlushCache"
+];
+
+#[derive(new, Clone)]
+pub struct SyncedLayer {
+    client: TonClient
+}
+
+impl<S> Layer<S> for SyncedLayer {
+    type Service = SyncedService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SyncedService { inner, client: self.client.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct SyncedService<S> {
+    inner: S,
+    client: TonClient
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for SyncedService<S>
+    where S: Service<Request<ReqBody>, Response = Response<BoxBody>>,
+          S::Future: Send + 'static,
+          S::Error: Send + 'static {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let path = req.uri().path();
+        if path.starts_with(GATED_PREFIX) && !UNGATED_PATHS.contains(&path) && !self.client.is_synced() {
+            return ready(Ok(error::unavailable("ton client is not synchronized").to_http())).boxed();
+        }
+
+        self.inner.call(req).boxed()
+    }
+}
@@ -1,10 +1,15 @@
 use std::str::FromStr;
 use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use num_bigint::BigInt;
+use toner::tlb::bits::de::unpack_bytes;
+use toner::ton::boc::BoC;
 use tonlibjson_client::address::{AccountAddressData};
 use tonlibjson_client::block;
 use tonlibjson_client::block::{MsgBoxedData, MsgDataDecryptedText, MsgDataEncryptedText, MsgDataRaw, MsgDataText};
 use crate::ton::get_account_state_response::AccountState;
 use crate::ton::message::MsgData;
+use crate::ton::stack_entry::Entry;
 
 tonic::include_proto!("ton");
 
@@ -34,6 +39,52 @@ impl From<BlockIdExt> for block::TonBlockIdExt {
     }
 }
 
+impl From<block::BlocksHeader> for BlockHeader {
+    fn from(value: block::BlocksHeader) -> Self {
+        Self {
+            id: Some(value.id.into()),
+            global_id: value.global_id,
+            version: value.version,
+            flags: value.flags,
+            after_merge: value.after_merge,
+            after_split: value.after_split,
+            before_split: value.before_split,
+            want_merge: value.want_merge,
+            want_split: value.want_split,
+            validator_list_hash_short: value.validator_list_hash_short,
+            catchain_seqno: value.catchain_seqno,
+            min_ref_mc_seqno: value.min_ref_mc_seqno,
+            is_key_block: value.is_key_block,
+            prev_key_block_seqno: value.prev_key_block_seqno,
+            start_lt: value.start_lt,
+            end_lt: value.end_lt,
+            gen_utime: value.gen_utime,
+            vert_seqno: value.vert_seqno,
+            prev_blocks: value.prev_blocks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl BlockHeader {
+    pub fn masked(self, paths: &[String]) -> Self {
+        let mut masked = Self::default();
+
+        macro_rules! keep {
+            ($($field:ident),*) => {
+                $(if paths.iter().any(|path| path == stringify!($field)) {
+                    masked.$field = self.$field;
+                })*
+            };
+        }
+
+        keep!(id, global_id, version, flags, after_merge, after_split, before_split, want_merge, want_split,
+            validator_list_hash_short, catchain_seqno, min_ref_mc_seqno, is_key_block, prev_key_block_seqno,
+            start_lt, end_lt, gen_utime, vert_seqno, prev_blocks);
+
+        masked
+    }
+}
+
 impl From<(i32, block::BlocksShortTxId)> for TransactionId {
     fn from((chain_id, value): (i32, block::BlocksShortTxId)) -> Self {
         let address = value.clone().into_internal_string(chain_id);
@@ -41,7 +92,9 @@ impl From<(i32, block::BlocksShortTxId)> for TransactionId {
         Self {
             account_address: address,
             lt: value.lt,
-            hash: value.hash
+            hash: value.hash,
+            cursor: None,
+            has_more: false
         }
     }
 }
@@ -51,7 +104,9 @@ impl From<(&AccountAddressData, block::InternalTransactionId)> for TransactionId
         Self {
             account_address: account_address.to_raw_string(),
             lt: tx_id.lt,
-            hash: tx_id.hash
+            hash: tx_id.hash,
+            cursor: None,
+            has_more: false
         }
     }
 }
@@ -74,10 +129,26 @@ impl From<PartialTransactionId> for block::InternalTransactionId {
     }
 }
 
-impl From<block::RawFullAccountState> for AccountState {
-    fn from(value: block::RawFullAccountState) -> Self {
-        if !value.code.is_empty() {
+fn cell_hash(boc: &str) -> anyhow::Result<String> {
+    if boc.is_empty() {
+        return Ok(String::new());
+    }
+
+    let boc: BoC = unpack_bytes(STANDARD.decode(boc)?)
+        .map_err(|e| anyhow!("invalid boc: {}", e))?;
+    let root = boc.single_root().ok_or_else(|| anyhow!("boc must contain a single root cell"))?;
+
+    Ok(STANDARD.encode(root.hash()))
+}
+
+impl TryFrom<block::RawFullAccountState> for AccountState {
+    type Error = anyhow::Error;
+
+    fn try_from(value: block::RawFullAccountState) -> Result<Self, Self::Error> {
+        Ok(if !value.code.is_empty() {
             AccountState::Active(ActiveAccountState {
+                code_hash: cell_hash(&value.code)?,
+                data_hash: cell_hash(&value.data)?,
                 code: value.code,
                 data: value.data
             })
@@ -87,6 +158,20 @@ impl From<block::RawFullAccountState> for AccountState {
             })
         } else {
             AccountState::Uninitialized(UninitializedAccountState {})
+        })
+    }
+}
+
+impl From<&block::RawFullAccountState> for AccountStatus {
+    fn from(value: &block::RawFullAccountState) -> Self {
+        if !value.code.is_empty() {
+            AccountStatus::Active
+        } else if !value.frozen_hash.is_empty() {
+            AccountStatus::Frozen
+        } else if value.last_transaction_id.is_some() {
+            AccountStatus::Uninit
+        } else {
+            AccountStatus::Nonexist
         }
     }
 }
@@ -99,6 +184,73 @@ impl From<block::TvmCell> for TvmCell {
     }
 }
 
+impl From<TvmCell> for block::TvmCell {
+    fn from(value: TvmCell) -> Self {
+        Self {
+            bytes: value.bytes
+        }
+    }
+}
+
+impl TryFrom<block::TvmBoxedStackEntry> for StackEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(value: block::TvmBoxedStackEntry) -> Result<Self, Self::Error> {
+        let entry = match value {
+            block::TvmBoxedStackEntry::TvmStackEntryNumber(number) => {
+                Entry::Number(BigInt::from_str(&number.number.number)?.to_signed_bytes_be())
+            },
+            block::TvmBoxedStackEntry::TvmStackEntryCell(cell) => Entry::Cell(cell.cell.into()),
+            block::TvmBoxedStackEntry::TvmStackEntrySlice(slice) => Entry::Slice(TvmCell { bytes: slice.slice.bytes }),
+            block::TvmBoxedStackEntry::TvmStackEntryTuple(tuple) => Entry::Tuple(tuple.tuple.elements.try_into()?),
+            block::TvmBoxedStackEntry::TvmStackEntryList(list) => Entry::List(list.list.elements.try_into()?),
+            _ => return Err(anyhow!("unsupported stack entry"))
+        };
+
+        Ok(Self { entry: Some(entry) })
+    }
+}
+
+impl TryFrom<StackEntry> for block::TvmBoxedStackEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(value: StackEntry) -> Result<Self, Self::Error> {
+        Ok(match value.entry.ok_or_else(|| anyhow!("stack entry is empty"))? {
+            Entry::Number(bytes) => {
+                let number = BigInt::from_signed_bytes_be(&bytes);
+                let bound = BigInt::from(1) << 256;
+                if number >= bound || number < -bound {
+                    return Err(anyhow!("stack number does not fit into 257 bits"));
+                }
+
+                Self::TvmStackEntryNumber(block::TvmStackEntryNumber {
+                    number: block::TvmNumberDecimal { number: number.to_string() }
+                })
+            },
+            Entry::Cell(cell) => Self::TvmStackEntryCell(block::TvmStackEntryCell { cell: cell.into() }),
+            Entry::Slice(slice) => Self::TvmStackEntrySlice(block::TvmStackEntrySlice { slice: block::TvmSlice { bytes: slice.bytes } }),
+            Entry::Tuple(tuple) => Self::TvmStackEntryTuple(block::TvmStackEntryTuple { tuple: block::TvmTuple { elements: tuple.try_into()? } }),
+            Entry::List(list) => Self::TvmStackEntryList(block::TvmStackEntryList { list: block::TvmList { elements: list.try_into()? } })
+        })
+    }
+}
+
+impl TryFrom<Vec<block::TvmBoxedStackEntry>> for StackEntries {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<block::TvmBoxedStackEntry>) -> Result<Self, Self::Error> {
+        Ok(Self { elements: value.into_iter().map(TryInto::try_into).collect::<Result<_, _>>()? })
+    }
+}
+
+impl TryFrom<StackEntries> for Vec<block::TvmBoxedStackEntry> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: StackEntries) -> Result<Self, Self::Error> {
+        value.elements.into_iter().map(TryInto::try_into).collect()
+    }
+}
+
 impl From<MsgBoxedData> for MsgData {
     fn from(value: MsgBoxedData) -> Self {
 
@@ -111,8 +263,32 @@ impl From<MsgBoxedData> for MsgData {
     }
 }
 
+impl From<block::Fees> for Fees {
+    fn from(value: block::Fees) -> Self {
+        Self {
+            in_fwd_fee: value.in_fwd_fee,
+            storage_fee: value.storage_fee,
+            gas_fee: value.gas_fee,
+            fwd_fee: value.fwd_fee
+        }
+    }
+}
+
 impl From<block::RawMessage> for Message {
     fn from(value: block::RawMessage) -> Self {
+        let r#type = match (&value.source.account_address, &value.destination.account_address) {
+            (None, _) => MessageType::ExternalIn,
+            (_, None) => MessageType::ExternalOut,
+            _ => MessageType::Internal
+        };
+        // tonlib renders the destination of an internal message as bounceable or non-bounceable according to its bounce flag
+        let bounce = match r#type {
+            MessageType::Internal => value.destination.account_address.as_deref()
+                .and_then(|address| AccountAddressData::from_str(address).ok())
+                .and_then(|address| address.is_bounceable()),
+            _ => None
+        };
+
         Self {
             source: value.source.account_address.map(|s| s.to_string()),
             destination: value.destination.account_address.map(|s| s.to_string()),
@@ -122,6 +298,8 @@ impl From<block::RawMessage> for Message {
             created_lt: value.created_lt,
             body_hash: value.body_hash.clone(),
             msg_data: Some(value.msg_data.into()),
+            r#type: r#type.into(),
+            bounce
         }
     }
 }
@@ -137,6 +315,7 @@ impl From<(&AccountAddressData, block::RawTransaction)> for Transaction {
             other_fee: value.other_fee,
             in_msg: value.in_msg.map(|m| m.into()),
             out_msgs: value.out_msgs.into_iter().map(Into::into).collect(),
+            r#type: None
         }
     }
 }
@@ -160,6 +339,85 @@ impl TryFrom<(i32, block::RawTransaction)> for Transaction {
             other_fee: value.other_fee,
             in_msg: value.in_msg.map(|m| m.into()),
             out_msgs: value.out_msgs.into_iter().map(Into::into).collect(),
+            r#type: None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use num_bigint::BigInt;
+    use tonlibjson_client::block;
+    use crate::ton::{BlockHeader, BlockIdExt, StackEntry};
+    use crate::ton::stack_entry::Entry;
+
+    fn number(value: &str) -> block::TvmBoxedStackEntry {
+        block::TvmBoxedStackEntry::TvmStackEntryNumber(block::TvmStackEntryNumber {
+            number: block::TvmNumberDecimal { number: value.to_owned() }
         })
     }
+
+    fn decimal(entry: block::TvmBoxedStackEntry) -> String {
+        let block::TvmBoxedStackEntry::TvmStackEntryNumber(number) = entry else { panic!("not a number") };
+
+        number.number.number
+    }
+
+    #[test]
+    fn stack_number_round_trips_up_to_257_bits() {
+        let max = (BigInt::from(1) << 256) - 1;
+        let min = -(BigInt::from(1) << 256);
+
+        for value in ["0", "-1", "42", "-9223372036854775809", max.to_string().as_str(), min.to_string().as_str()] {
+            let entry: StackEntry = number(value).try_into().unwrap();
+            let back: block::TvmBoxedStackEntry = entry.try_into().unwrap();
+
+            assert_eq!(decimal(back), value);
+        }
+    }
+
+    #[test]
+    fn stack_number_is_big_endian_twos_complement() {
+        let entry: StackEntry = number("-2").try_into().unwrap();
+
+        assert_eq!(entry.entry, Some(Entry::Number(vec![0xFE])));
+        assert_eq!(BigInt::from_str("256").unwrap().to_signed_bytes_be(), vec![0x01, 0x00]);
+    }
+
+    #[test]
+    fn nested_stack_entries_round_trip() {
+        let cell = block::TvmBoxedStackEntry::TvmStackEntryCell(block::TvmStackEntryCell { cell: block::TvmCell { bytes: "te6c".to_owned() } });
+        let tuple = block::TvmBoxedStackEntry::TvmStackEntryTuple(block::TvmStackEntryTuple { tuple: block::TvmTuple { elements: vec![number("1"), cell.clone()] } });
+        let list = block::TvmBoxedStackEntry::TvmStackEntryList(block::TvmStackEntryList { list: block::TvmList { elements: vec![tuple, number("-7")] } });
+
+        let entry: StackEntry = list.clone().try_into().unwrap();
+        let Some(Entry::List(ref elements)) = entry.entry else { panic!("not a list") };
+        assert!(matches!(elements.elements[0].entry, Some(Entry::Tuple(_))));
+
+        let back: block::TvmBoxedStackEntry = entry.try_into().unwrap();
+        assert_eq!(serde_json::to_value(back).unwrap(), serde_json::to_value(list).unwrap());
+    }
+
+    #[test]
+    fn stack_number_wider_than_257_bits_is_rejected() {
+        let entry = StackEntry { entry: Some(Entry::Number((BigInt::from(1) << 256).to_signed_bytes_be())) };
+
+        assert!(block::TvmBoxedStackEntry::try_from(entry).is_err());
+    }
+
+    #[test]
+    fn block_header_masked_keeps_only_listed_fields() {
+        let header = BlockHeader {
+            id: Some(BlockIdExt::default()),
+            gen_utime: 1700000000,
+            start_lt: 42,
+            is_key_block: true,
+            ..Default::default()
+        };
+
+        let masked = header.masked(&["gen_utime".to_owned(), "is_key_block".to_owned()]);
+
+        assert_eq!(masked, BlockHeader { gen_utime: 1700000000, is_key_block: true, ..Default::default() });
+    }
 }
@@ -0,0 +1,24 @@
+use std::fmt::Debug;
+use tonlibjson_client::debug_trace;
+
+const TARGET: &str = "ton_grpc::trace";
+
+pub fn request<T: Debug>(message: T) -> T {
+    if tracing::enabled!(target: TARGET, tracing::Level::TRACE) {
+        if let Some(message) = debug_trace::debug(&message) {
+            tracing::trace!(target: TARGET, request = message, "grpc request");
+        }
+    }
+
+    message
+}
+
+pub fn response<T: Debug>(message: T) -> T {
+    if tracing::enabled!(target: TARGET, tracing::Level::TRACE) {
+        if let Some(message) = debug_trace::debug(&message) {
+            tracing::trace!(target: TARGET, response = message, "grpc response");
+        }
+    }
+
+    message
+}
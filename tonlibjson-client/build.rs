@@ -47,7 +47,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .build())
             .build())
         .configure_full("raw.fullAccountState", configure_type()
-            .derives(vec!["Deserialize"])
+            .derives(vec!["Clone", "Deserialize"])
             .field("balance", configure_field()
                 .optional()
                 .deserialize_with("deserialize_ton_account_balance")
@@ -69,12 +69,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .configure("blocks.getMasterchainInfo", vec!["Clone", "Default", "Serialize", "new"])
         .configure("blocks.lookupBlock", vec!["Clone", "Serialize", "new", "Hash", "Eq", "PartialEq"])
         .configure("blocks.getShards", vec!["Clone", "Serialize", "new"])
+        .configure_full("blocks.getShardBlockProof", configure_type().derives(vec!["Clone", "Serialize", "new"])
+            .field("from", configure_field().optional().build())
+            .build()
+        )
+        .configure("blocks.getMasterchainBlockSignatures", vec!["Clone", "Serialize", "new"])
         .configure("blocks.getTransactions", vec!["Clone", "Serialize", "new"])
         .configure("raw.sendMessage", vec!["Serialize", "new"])
         .configure("raw.sendMessageReturnHash", vec!["Serialize", "new"])
 
         .configure("smc.load", vec!["Clone", "Serialize", "new"])
         .configure("smc.runGetMethod", vec!["Clone", "Serialize", "new"])
+        .configure("raw.createQuery", vec!["Clone", "Serialize", "new"])
+        .configure("query.estimateFees", vec!["Clone", "Serialize", "new"])
+        .configure("getConfigParam", vec!["Clone", "Serialize", "new"])
 
         .configure_full("raw.getTransactionsV2", configure_type().derives(vec!["Clone", "Serialize", "new"])
             .field("private_key", configure_field().skip().build())
@@ -87,6 +87,7 @@ const CRC16: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_XMODEM);
 
 const BOUNCABLE: u8 = 0x11;
 const NON_BOUNCABLE: u8 = 0x51;
+const TEST_ONLY: u8 = 0x80;
 
 impl Display for AccountAddressData {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -120,10 +121,39 @@ impl AccountAddressData {
         }
     }
 
+    pub fn is_test_only(&self) -> bool {
+        self.flags.is_some_and(|flags| flags & TEST_ONLY != 0)
+    }
+
+    pub fn is_bounceable(&self) -> Option<bool> {
+        self.flags.map(|flags| flags & !TEST_ONLY == BOUNCABLE)
+    }
+
+    pub fn test_only(&self) -> Self {
+        Self {
+            flags: Some(self.flags.unwrap_or(BOUNCABLE) | TEST_ONLY),
+            chain_id: self.chain_id,
+            bytes: self.bytes
+        }
+    }
+
+    /// FromStr doesn't check the crc16 of friendly addresses, this compares `s` with the re-encoded form.
+    pub fn checksum_matches(&self, s: &str) -> bool {
+        self.flags.is_none() || self.to_flagged_string() == s.replace('+', "-").replace('/', "_")
+    }
+
     pub fn into_shard_context(self) -> ShardContextAccountAddress {
         ShardContextAccountAddress { bytes: self.bytes }
     }
 
+    /// The leading 64 bits of the account id with the tag bit set, block lookups resolve it to the shard holding the account.
+    pub fn shard_prefix(&self) -> i64 {
+        let mut prefix = [0; 8];
+        prefix.copy_from_slice(&self.bytes[..8]);
+
+        (u64::from_be_bytes(prefix) | 1) as i64
+    }
+
     pub fn to_raw_string(&self) -> String {
         format!("{}:{}", self.chain_id, hex::encode(self.bytes))
     }
@@ -247,6 +277,15 @@ mod tests {
         assert_eq!("-1:0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b", actual)
     }
 
+    #[test]
+    fn account_address_shard_prefix() {
+        let mut bytes = [0; 32];
+        bytes[0] = 0xc0;
+
+        assert_eq!(AccountAddressData { chain_id: 0, bytes, flags: None }.shard_prefix() as u64, 0xc000_0000_0000_0001);
+        assert_eq!(AccountAddressData { chain_id: 0, bytes: [0; 32], flags: None }.shard_prefix(), 1);
+    }
+
     #[test]
     fn account_address_correct() {
         assert!(AccountAddressData::from_str("EQBO_mAVkaHxt6Ibz7wqIJ_UIDmxZBFcgkk7fvIzkh7l42wO").is_ok())
@@ -1,5 +1,6 @@
 use std::{pin::Pin, task::{Context, Poll}};
 use std::future::Future;
+use std::borrow::Borrow;
 use std::hash::Hash;
 use futures::{TryFutureExt, FutureExt};
 use derive_new::new;
@@ -15,7 +16,7 @@ use crate::router::{Router, Routable};
 pub(crate) struct Balance<D>
     where
         D: Discover<Service=CursorClient, Error = anyhow::Error> + Unpin,
-        D::Key: Eq + Hash,
+        D::Key: Eq + Hash + Borrow<str>,
 {
     router: Router<CursorClient, D>
 }
@@ -24,7 +25,7 @@ impl<R, D> Service<R> for Balance<D>
     where
         R: Routable + Callable<InnerClient>,
         D: Discover<Service=CursorClient, Error = anyhow::Error> + Unpin,
-        D::Key: Eq + Hash,
+        D::Key: Eq + Hash + Borrow<str>,
 {
     type Response = R::Response;
     type Error = Error;
@@ -48,7 +49,7 @@ impl<R, D> Service<R> for Balance<D>
 impl<D> Service<Specialized<BlocksGetMasterchainInfo>> for Balance<D>
     where
         D: Discover<Service=CursorClient, Error = anyhow::Error> + Unpin,
-        D::Key: Eq + Hash,
+        D::Key: Eq + Hash + Borrow<str>,
 {
     type Response = BlocksMasterchainInfo;
     type Error = Error;
@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use derive_new::new;
+use tokio::time::Instant;
+use crate::error::Error;
+
+#[derive(new, Debug, Clone, Copy)]
+pub struct BlacklistPolicy {
+    max_failures: usize,
+    window: Duration
+}
+
+pub(crate) struct Blacklist {
+    id: Cow<'static, str>,
+    policy: BlacklistPolicy,
+    failures: Mutex<VecDeque<Instant>>,
+    blacklisted: AtomicBool
+}
+
+impl Blacklist {
+    pub(crate) fn new(id: Cow<'static, str>, policy: BlacklistPolicy) -> Self {
+        metrics::describe_gauge!("ton_liteserver_blacklisted", "Whether the liteserver is removed from the rotation after repeated failures");
+        metrics::describe_counter!("ton_liteserver_blacklist_count", "Count of times the liteserver was removed from the rotation");
+
+        metrics::gauge!("ton_liteserver_blacklisted", "liteserver_id" => id.clone()).set(0.);
+
+        Self {
+            id,
+            policy,
+            failures: Default::default(),
+            blacklisted: AtomicBool::new(false)
+        }
+    }
+
+    pub(crate) fn is_blacklisted(&self) -> bool {
+        self.blacklisted.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_failure(&self) {
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+
+        failures.push_back(now);
+        while failures.front().is_some_and(|at| now.duration_since(*at) > self.policy.window) {
+            failures.pop_front();
+        }
+
+        if failures.len() >= self.policy.max_failures && !self.blacklisted.swap(true, Ordering::Relaxed) {
+            tracing::warn!(liteserver_id = ?self.id, failures = failures.len(), "liteserver blacklisted");
+
+            metrics::gauge!("ton_liteserver_blacklisted", "liteserver_id" => self.id.clone()).set(1.);
+            metrics::counter!("ton_liteserver_blacklist_count", "liteserver_id" => self.id.clone()).increment(1);
+        }
+    }
+
+    pub(crate) fn readmit(&self) {
+        self.failures.lock().unwrap().clear();
+
+        if self.blacklisted.swap(false, Ordering::Relaxed) {
+            tracing::info!(liteserver_id = ?self.id, "liteserver readmitted");
+
+            metrics::gauge!("ton_liteserver_blacklisted", "liteserver_id" => self.id.clone()).set(0.);
+        }
+    }
+}
+
+/// Whether `e` tells something about the health of the liteserver rather than about the request itself.
+/// Application errors such as not found or a failed get-method are the client's problem and must not count.
+pub(crate) fn is_liteserver_failure(e: &anyhow::Error) -> bool {
+    if Error::is_timeout(e) {
+        return true
+    }
+
+    if e.chain().any(|cause| matches!(cause.downcast_ref::<Error>(), Some(Error::LiteserverUnavailable(_)))) {
+        return true
+    }
+
+    match Error::ton_error(e) {
+        Some(error) => error.is_network(),
+        None => !e.chain().any(|cause| cause.is::<serde_json::Error>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::time::Duration;
+    use anyhow::anyhow;
+    use serde_json::json;
+    use crate::blacklist::{is_liteserver_failure, Blacklist, BlacklistPolicy};
+    use crate::block::TonError;
+    use crate::error::Error;
+
+    #[tokio::test(start_paused = true)]
+    async fn blacklists_after_max_failures_within_window() {
+        let blacklist = Blacklist::new(Cow::Borrowed("first"), BlacklistPolicy::new(3, Duration::from_secs(10)));
+
+        blacklist.record_failure();
+        blacklist.record_failure();
+        assert!(!blacklist.is_blacklisted());
+
+        blacklist.record_failure();
+        assert!(blacklist.is_blacklisted());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failures_outside_of_window_are_forgotten() {
+        let blacklist = Blacklist::new(Cow::Borrowed("first"), BlacklistPolicy::new(2, Duration::from_secs(10)));
+
+        blacklist.record_failure();
+        tokio::time::advance(Duration::from_secs(11)).await;
+        blacklist.record_failure();
+
+        assert!(!blacklist.is_blacklisted());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn readmit_clears_failures() {
+        let blacklist = Blacklist::new(Cow::Borrowed("first"), BlacklistPolicy::new(2, Duration::from_secs(10)));
+
+        blacklist.record_failure();
+        blacklist.record_failure();
+        blacklist.readmit();
+        assert!(!blacklist.is_blacklisted());
+
+        blacklist.record_failure();
+        assert!(!blacklist.is_blacklisted());
+    }
+
+    fn ton_error(code: i32, message: &str) -> anyhow::Error {
+        anyhow!(serde_json::from_value::<TonError>(json!({"code": code, "message": message})).unwrap())
+    }
+
+    #[test]
+    fn application_errors_are_not_failures() {
+        assert!(!is_liteserver_failure(&ton_error(500, "LITE_SERVER_UNKNOWN: cannot compute block with specified transaction")));
+        assert!(!is_liteserver_failure(&ton_error(400, "Failed to parse account address")));
+        assert!(!is_liteserver_failure(&ton_error(404, "account not found")));
+    }
+
+    #[test]
+    fn transport_errors_and_timeouts_are_failures() {
+        assert!(is_liteserver_failure(&ton_error(500, "LITE_SERVER_NETWORK")));
+        assert!(is_liteserver_failure(&ton_error(500, "adnl query timeout")));
+        assert!(is_liteserver_failure(&anyhow!(Error::LiteserverUnavailable("first".to_owned()))));
+        assert!(is_liteserver_failure(&anyhow!("tonlib client is gone")));
+    }
+}
@@ -132,6 +132,7 @@ impl Routable for BlocksLookupBlock {
     fn route(&self) -> Route {
         let criteria = match self.mode {
             2 => BlockCriteria::LogicalTime(self.lt),
+            4 => return Route::Latest,
             _ => BlockCriteria::Seqno { shard: self.id.shard, seqno: self.id.seqno }
         };
 
@@ -147,6 +148,10 @@ impl BlocksLookupBlock {
     pub fn logical_time(id: TonBlockId, lt: i64) -> Self {
         Self { mode: 2, id, lt, utime: 0 }
     }
+
+    pub fn unixtime(id: TonBlockId, utime: i32) -> Self {
+        Self { mode: 4, id, lt: 0, utime }
+    }
 }
 
 impl Routable for BlocksGetShards {
@@ -155,6 +160,20 @@ impl Routable for BlocksGetShards {
     }
 }
 
+impl Routable for BlocksGetShardBlockProof {
+    fn route(&self) -> Route {
+        let block = self.from.as_ref().unwrap_or(&self.id);
+
+        Route::Block { chain: block.workchain, criteria: BlockCriteria::Seqno { shard: block.shard, seqno: block.seqno } }
+    }
+}
+
+impl Routable for BlocksGetMasterchainBlockSignatures {
+    fn route(&self) -> Route {
+        Route::Block { chain: -1, criteria: BlockCriteria::Seqno { shard: i64::MIN, seqno: self.seqno } }
+    }
+}
+
 impl BlocksGetTransactionsExt {
     pub fn unverified(block_id: TonBlockIdExt, after: Option<BlocksAccountTransactionId>, reverse: bool, count: i32) -> Self {
         let count = if count > 256 { 256 } else { count };
@@ -255,9 +274,21 @@ impl TryFrom<&RawTransaction> for BlocksAccountTransactionId {
 impl Routable for RawSendMessage {}
 impl Routable for RawSendMessageReturnHash {}
 impl Routable for SmcLoad {}
+impl Routable for RawCreateQuery {}
+impl Routable for GetConfigParam {}
 
 impl SmcBoxedMethodId {
     pub fn by_name(name: &str) -> Self { Self::SmcMethodIdName(SmcMethodIdName { name: name.to_owned() })}
+
+    pub fn by_number(number: i32) -> Self { Self::SmcMethodIdNumber(SmcMethodIdNumber { number }) }
+}
+
+impl From<String> for SmcBoxedMethodId {
+    fn from(name: String) -> Self { Self::SmcMethodIdName(SmcMethodIdName { name }) }
+}
+
+impl From<i32> for SmcBoxedMethodId {
+    fn from(number: i32) -> Self { Self::by_number(number) }
 }
 
 
@@ -299,6 +330,32 @@ impl Display for TonError {
     }
 }
 
+impl TonError {
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.message.contains("not found")
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        self.message.to_lowercase().contains("timeout")
+    }
+
+    /// Tonlib reports these when it cannot reach the liteserver, as opposed to the liteserver rejecting the query.
+    pub fn is_network(&self) -> bool {
+        let message = self.message.to_lowercase();
+
+        ["lite_server_network", "connection", "adnl"].iter().any(|pattern| message.contains(pattern))
+    }
+
+    /// Liteservers answer with these when the requested history was already pruned.
+    pub fn is_pruned(&self) -> bool {
+        ["not in db", "already gc", "cannot load block"].iter().any(|pattern| self.message.contains(pattern))
+    }
+}
+
 impl StdError for TonError {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
@@ -0,0 +1,233 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use quick_cache::sync::Cache;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheStats {
+    pub name: &'static str,
+    pub size: usize,
+    pub hits: u64,
+    pub misses: u64
+}
+
+pub(crate) struct TtlCache<K, V> {
+    name: &'static str,
+    inner: Cache<K, (Instant, V)>,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64
+}
+
+impl<K, V> TtlCache<K, V> where K: Eq + Hash + Clone, V: Clone {
+    pub(crate) fn new(name: &'static str, ttl: Duration, capacity: usize) -> Self {
+        metrics::describe_counter!("ton_cache_hits_total", "Count of cache hits");
+        metrics::describe_counter!("ton_cache_misses_total", "Count of cache misses, including expired entries");
+        metrics::describe_gauge!("ton_cache_size", "Number of entries in the cache");
+
+        Self { name, inner: Cache::new(capacity), ttl, hits: Default::default(), misses: Default::default() }
+    }
+
+    pub(crate) fn get(&self, key: &K) -> Option<V> {
+        let value = self.get_fresh(key);
+        self.record(value.is_some());
+
+        value
+    }
+
+    /// The fresh value of `key`, `value` is inserted when there is none.
+    /// Callers racing for the same key all get the value that was inserted first.
+    pub(crate) fn get_or_insert(&self, key: K, value: V) -> V {
+        self.get_fresh(&key);
+
+        let mut inserted = false;
+        let (_, value) = self.inner
+            .get_or_insert_with(&key, || {
+                inserted = true;
+
+                Ok::<_, Infallible>((Instant::now(), value))
+            })
+            .unwrap_or_else(|e| match e {});
+        self.record(!inserted);
+        if inserted {
+            metrics::gauge!("ton_cache_size", "cache" => self.name).set(self.inner.len() as f64);
+        }
+
+        value
+    }
+
+    fn record(&self, hit: bool) {
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            metrics::counter!("ton_cache_hits_total", "cache" => self.name).increment(1);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            metrics::counter!("ton_cache_misses_total", "cache" => self.name).increment(1);
+        }
+    }
+
+    pub(crate) fn insert(&self, key: K, value: V) {
+        self.inner.insert(key, (Instant::now(), value));
+
+        metrics::gauge!("ton_cache_size", "cache" => self.name).set(self.inner.len() as f64);
+    }
+
+    fn get_fresh(&self, key: &K) -> Option<V> {
+        let (inserted_at, value) = self.inner.get(key)?;
+        if inserted_at.elapsed() > self.ttl {
+            self.inner.remove(key);
+            metrics::gauge!("ton_cache_size", "cache" => self.name).set(self.inner.len() as f64);
+
+            return None;
+        }
+
+        Some(value)
+    }
+}
+
+/// Holds a single value, callers missing it concurrently wait for the first one to refresh it instead of fetching it again.
+pub(crate) struct SingleFlightCache<V> {
+    cache: TtlCache<(), V>,
+    refresh: tokio::sync::Mutex<()>
+}
+
+impl<V> SingleFlightCache<V> where V: Clone {
+    pub(crate) fn new(name: &'static str, ttl: Duration) -> Self {
+        Self { cache: TtlCache::new(name, ttl, 1), refresh: Default::default() }
+    }
+
+    pub(crate) async fn get_or_try_refresh<F, Fut>(&self, refresh: F) -> anyhow::Result<V>
+        where F: FnOnce() -> Fut, Fut: Future<Output=anyhow::Result<V>> {
+        if let Some(value) = self.cache.get(&()) {
+            return Ok(value);
+        }
+
+        let _guard = self.refresh.lock().await;
+        if let Some(value) = self.cache.get_fresh(&()) {
+            return Ok(value);
+        }
+
+        let value = refresh().await?;
+        self.cache.insert((), value.clone());
+
+        Ok(value)
+    }
+}
+
+pub(crate) trait CacheControl: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn stats(&self) -> CacheStats;
+    fn clear(&self);
+}
+
+impl<K, V> CacheControl for TtlCache<K, V> where K: Eq + Hash + Clone + Send + Sync, V: Clone + Send + Sync {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            name: self.name,
+            size: self.inner.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed)
+        }
+    }
+
+    fn clear(&self) {
+        self.inner.clear();
+
+        metrics::gauge!("ton_cache_size", "cache" => self.name).set(0.);
+    }
+}
+
+impl<V> CacheControl for SingleFlightCache<V> where V: Clone + Send + Sync {
+    fn name(&self) -> &'static str {
+        self.cache.name()
+    }
+
+    fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    fn clear(&self) {
+        self.cache.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    use crate::cache::{CacheControl, SingleFlightCache, TtlCache};
+
+    #[test]
+    fn get_returns_inserted_value() {
+        let cache = TtlCache::new("test", Duration::from_secs(60), 16);
+
+        cache.insert("key".to_owned(), 42);
+
+        assert_eq!(cache.get(&"key".to_owned()), Some(42));
+    }
+
+    #[test]
+    fn clear_removes_values_and_keeps_stats() {
+        let cache = TtlCache::new("test", Duration::from_secs(60), 16);
+
+        cache.insert("key".to_owned(), 42);
+        cache.get(&"key".to_owned());
+        cache.clear();
+        cache.get(&"key".to_owned());
+
+        let stats = cache.stats();
+        assert_eq!((stats.size, stats.hits, stats.misses), (0, 1, 1));
+    }
+
+    #[test]
+    fn get_or_insert_keeps_first_value() {
+        let cache = TtlCache::new("test", Duration::from_secs(60), 16);
+
+        assert_eq!(cache.get_or_insert("key".to_owned(), 1), 1);
+        assert_eq!(cache.get_or_insert("key".to_owned(), 2), 1);
+
+        let stats = cache.stats();
+        assert_eq!((stats.size, stats.hits, stats.misses), (1, 1, 1));
+    }
+
+    #[test]
+    fn get_drops_expired_value() {
+        let cache = TtlCache::new("test", Duration::ZERO, 16);
+
+        cache.insert("key".to_owned(), 42);
+        std::thread::sleep(Duration::from_millis(1));
+
+        assert_eq!(cache.get(&"key".to_owned()), None);
+    }
+
+    #[tokio::test]
+    async fn single_flight_cache_refreshes_once_for_concurrent_callers() {
+        let cache = SingleFlightCache::new("test", Duration::from_secs(60));
+        let calls = AtomicU32::new(0);
+        let refresh = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+
+            anyhow::Ok(42)
+        };
+
+        let (a, b) = tokio::join!(cache.get_or_try_refresh(refresh), cache.get_or_try_refresh(refresh));
+
+        assert_eq!((a.unwrap(), b.unwrap()), (42, 42));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn single_flight_cache_keeps_nothing_on_error() {
+        let cache = SingleFlightCache::new("test", Duration::from_secs(60));
+
+        assert!(cache.get_or_try_refresh(|| async { Err(anyhow::anyhow!("unavailable")) }).await.is_err());
+        assert_eq!(cache.get_or_try_refresh(|| async { anyhow::Ok(42) }).await.unwrap(), 42);
+    }
+}
@@ -11,6 +11,7 @@ use dashmap::DashMap;
 use tokio_util::sync::{CancellationToken, DropGuard};
 use uuid::Uuid;
 use crate::block::TonError;
+use crate::debug_trace;
 use crate::request::Requestable;
 
 #[derive(Debug)]
@@ -93,6 +94,12 @@ impl<R : Requestable> Service<R> for Client {
             return Box::pin(futures::future::ready(Err(anyhow!(to_string.unwrap_err()))));
         };
 
+        if tracing::enabled!(tracing::Level::TRACE) {
+            if let Some(query) = debug_trace::display(&query) {
+                tracing::trace!(request_id = %req.id, request = query, "liteserver request");
+            }
+        }
+
         let requests = Arc::clone(&self.responses);
         let (tx, rx) = tokio::sync::oneshot::channel::<Response>();
         requests.insert(req.id, tx);
@@ -109,6 +116,11 @@ impl<R : Requestable> Service<R> for Client {
             drop(drop_guard);
 
             let response = result??;
+            if tracing::enabled!(tracing::Level::TRACE) {
+                if let Some(data) = debug_trace::display(&response.data) {
+                    tracing::trace!(request_id = %req.id, response = data, "liteserver response");
+                }
+            }
 
             // TODO[akostylev0] refac!!
             if response.data["@type"] == "error" {
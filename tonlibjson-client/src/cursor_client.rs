@@ -4,6 +4,7 @@ use std::convert::Infallible;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::task::{Context, Poll};
 use std::time::Duration;
 use tower::{Service, ServiceExt};
@@ -23,15 +24,19 @@ use tower::load::PeakEwma;
 use tower::load::Load;
 use tracing::{instrument};
 use ton_client_utils::router::{BlockCriteria, Routed};
+use crate::blacklist::{is_liteserver_failure, Blacklist, BlacklistPolicy};
 use crate::block::{BlocksGetMasterchainInfo, BlocksGetShards, BlocksHeader, BlocksMasterchainInfo, Sync, TonBlockId, TonBlockIdExt};
 use crate::block::{BlocksLookupBlock, BlocksGetBlockHeader};
 use crate::client::Client;
 use crate::metric::ConcurrencyMetric;
+use crate::liteserver;
 use crate::request::{Specialized, Callable};
 use crate::shared::SharedService;
 
 pub(crate) type InnerClient = ConcurrencyMetric<ConcurrencyLimit<SharedService<PeakEwma<Client>>>>;
 
+const BLACKLIST_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
 type ChainId = i32;
 type ShardId = (i32, i64);
 
@@ -217,7 +222,8 @@ pub(crate) struct CursorClient {
     client: InnerClient,
 
     masterchain_info_rx: Receiver<Option<BlocksMasterchainInfo>>,
-    registry: Arc<Registry>
+    registry: Arc<Registry>,
+    blacklist: Option<Arc<Blacklist>>
 }
 
 impl Routed for CursorClient {
@@ -240,7 +246,7 @@ impl Routed for CursorClient {
 }
 
 impl CursorClient {
-    pub(crate) fn new(id: String, client: ConcurrencyLimit<SharedService<PeakEwma<Client>>>) -> Self {
+    pub(crate) fn new(id: String, client: ConcurrencyLimit<SharedService<PeakEwma<Client>>>, blacklist: Option<BlacklistPolicy>, network_head: Arc<AtomicI32>) -> Self {
         metrics::describe_counter!("ton_liteserver_last_seqno", "The seqno of the latest block that is available for the liteserver to sync");
         metrics::describe_counter!("ton_liteserver_synced_seqno", "The seqno of the last block with which the liteserver is actually synchronized");
         metrics::describe_counter!("ton_liteserver_first_seqno", "The seqno of the first block that is available for the liteserver to request");
@@ -251,16 +257,21 @@ impl CursorClient {
         let client = ConcurrencyMetric::new(client, id.clone());
         let (mtx, mrx) = tokio::sync::watch::channel(None);
         let mut mc_watcher = mtx.subscribe();
+        let blacklist = blacklist.map(|policy| Arc::new(Blacklist::new(id.clone(), policy)));
 
         let _self = Self {
             id,
             client,
 
             masterchain_info_rx: mrx,
-            registry: Default::default()
+            registry: Default::default(),
+            blacklist
         };
 
-        tokio::spawn(_self.last_block_loop(mtx));
+        if let Some(blacklist) = _self.blacklist.clone() {
+            tokio::spawn(Self::probe_loop(blacklist, _self.client.clone()));
+        }
+        tokio::spawn(_self.last_block_loop(mtx, network_head));
         let inner = _self.first_block_loop();
         tokio::spawn(async move {
             mc_watcher.changed().await.unwrap();
@@ -271,12 +282,12 @@ impl CursorClient {
         _self
     }
 
-    fn last_block_loop(&self, mtx: Sender<Option<BlocksMasterchainInfo>>) -> impl Future<Output = Infallible> {
+    fn last_block_loop(&self, mtx: Sender<Option<BlocksMasterchainInfo>>, network_head: Arc<AtomicI32>) -> impl Future<Output = Infallible> {
         let id = self.id.clone();
         let client = self.client.clone();
         let registry = self.registry.clone();
 
-        let discover = LastBlockDiscover::new(id, client, registry, mtx);
+        let discover = LastBlockDiscover::new(id, client, registry, mtx, network_head);
 
         discover.discover()
     }
@@ -291,6 +302,27 @@ impl CursorClient {
         discover.discover()
     }
 
+    async fn probe_loop(blacklist: Arc<Blacklist>, client: InnerClient) -> Never {
+        let mut timer = interval(BLACKLIST_PROBE_INTERVAL);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            timer.tick().await;
+
+            if !blacklist.is_blacklisted() {
+                continue;
+            }
+
+            if client.clone().oneshot(BlocksGetMasterchainInfo::default()).await.is_ok() {
+                blacklist.readmit();
+            }
+        }
+    }
+
+    fn is_blacklisted(&self) -> bool {
+        self.blacklist.as_ref().is_some_and(|blacklist| blacklist.is_blacklisted())
+    }
+
     fn edges_defined(&self) -> bool {
         let Some(master_shard_id) = self.masterchain_info_rx
             .borrow()
@@ -307,7 +339,7 @@ impl Service<Specialized<BlocksGetMasterchainInfo>> for CursorClient {
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
-        if self.masterchain_info_rx.borrow().is_some() && self.edges_defined() {
+        if self.masterchain_info_rx.borrow().is_some() && self.edges_defined() && !self.is_blacklisted() {
             Poll::Ready(Ok(()))
         } else {
             cx.waker().wake_by_ref();
@@ -317,6 +349,8 @@ impl Service<Specialized<BlocksGetMasterchainInfo>> for CursorClient {
     }
 
     fn call(&mut self, _: Specialized<BlocksGetMasterchainInfo>) -> Self::Future {
+        liteserver::record(&self.id);
+
         let response = self.masterchain_info_rx.borrow().as_ref().unwrap().clone();
 
         return ready(Ok(response)).boxed()
@@ -329,7 +363,7 @@ impl<R : Callable<InnerClient>> Service<R> for CursorClient {
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
-        if self.edges_defined() {
+        if self.edges_defined() && !self.is_blacklisted() {
             return Service::<BlocksGetMasterchainInfo>::poll_ready(&mut self.client, cx);
         }
 
@@ -339,7 +373,16 @@ impl<R : Callable<InnerClient>> Service<R> for CursorClient {
     }
 
     fn call(&mut self, req: R) -> Self::Future {
-        req.call(&mut self.client).map_err(|e| e.into().into()).boxed()
+        liteserver::record(&self.id);
+
+        let blacklist = self.blacklist.clone();
+
+        req.call(&mut self.client)
+            .map_err(|e| e.into().into())
+            .inspect_err(move |e: &anyhow::Error| if let Some(blacklist) = blacklist.filter(|_| is_liteserver_failure(e)) {
+                blacklist.record_failure()
+            })
+            .boxed()
     }
 }
 
@@ -500,11 +543,12 @@ struct LastBlockDiscover {
     registry: Arc<Registry>,
     current: Option<BlocksMasterchainInfo>,
     mtx: Sender<Option<BlocksMasterchainInfo>>,
-    last_block_tx: UnboundedSender<TonBlockIdExt>
+    last_block_tx: UnboundedSender<TonBlockIdExt>,
+    network_head: Arc<AtomicI32>
 }
 
 impl LastBlockDiscover {
-    fn new(id: Cow<'static, str>, client: InnerClient, registry: Arc<Registry>, mtx: Sender<Option<BlocksMasterchainInfo>>) -> Self {
+    fn new(id: Cow<'static, str>, client: InnerClient, registry: Arc<Registry>, mtx: Sender<Option<BlocksMasterchainInfo>>, network_head: Arc<AtomicI32>) -> Self {
         let (last_block_tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<TonBlockIdExt>();
 
         // TODO[akostylev0] find last available block
@@ -563,7 +607,7 @@ impl LastBlockDiscover {
             }
         });
 
-        Self { id, client, registry, current: None, mtx, last_block_tx }
+        Self { id, client, registry, current: None, mtx, last_block_tx, network_head }
     }
 
     async fn discover(mut self) -> Never {
@@ -586,6 +630,7 @@ impl LastBlockDiscover {
     async fn next(&mut self) -> Result<Option<BlocksMasterchainInfo>> {
         let mut info = (&mut self.client).oneshot(BlocksGetMasterchainInfo::new()).await?;
         metrics::counter!("ton_liteserver_last_seqno", "liteserver_id" => self.id.clone()).absolute(info.last.seqno as u64);
+        self.network_head.fetch_max(info.last.seqno, Ordering::Relaxed);
         if self.current.as_ref().is_some_and(|c| c == &info) {
             return Ok(None);
         }
@@ -0,0 +1,53 @@
+use std::fmt::{Debug, Display};
+use std::sync::OnceLock;
+
+static MAX_SIZE: OnceLock<usize> = OnceLock::new();
+
+pub fn enable(max_size: usize) {
+    let _ = MAX_SIZE.set(max_size);
+}
+
+/// Formats `value` when debug tracing is on. `tracing::enabled!` checks the caller's module,
+/// so callers check that their own TRACE events are enabled before formatting.
+pub fn debug<T: Debug + ?Sized>(value: &T) -> Option<String> {
+    max_size().map(|max_size| truncate(format!("{:?}", value), max_size))
+}
+
+pub fn display<T: Display + ?Sized>(value: &T) -> Option<String> {
+    max_size().map(|max_size| truncate(value.to_string(), max_size))
+}
+
+fn max_size() -> Option<usize> {
+    MAX_SIZE.get().copied()
+}
+
+fn truncate(mut value: String, max_size: usize) -> String {
+    if value.len() <= max_size {
+        return value;
+    }
+
+    let total = value.len();
+    let mut end = max_size;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    value.truncate(end);
+    value.push_str(&format!("... ({} bytes total)", total));
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug_trace::truncate;
+
+    #[test]
+    fn keeps_short_values() {
+        assert_eq!(truncate("short".to_owned(), 16), "short");
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        assert_eq!(truncate("ббб".to_owned(), 3), "б... (6 bytes total)");
+    }
+}
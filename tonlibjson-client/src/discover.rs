@@ -7,9 +7,12 @@ use std::{
     task::{Context, Poll},
 };
 use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::AtomicI32;
 use std::net::IpAddr;
 use std::path::PathBuf;
 use futures::{TryStreamExt, StreamExt};
+use futures::future::join_all;
 use tokio_stream::{Stream};
 use tower::discover::Change;
 use tower::load::PeakEwmaDiscover;
@@ -17,8 +20,10 @@ use tower::ServiceExt;
 use hickory_resolver::system_conf::read_system_conf;
 use hickory_resolver::TokioAsyncResolver;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
 use tokio::time::{Interval, MissedTickBehavior};
 use tokio_stream::wrappers::IntervalStream;
+use crate::blacklist::BlacklistPolicy;
 use crate::client::Client;
 use crate::cursor_client::CursorClient;
 use crate::ton_config::Liteserver;
@@ -42,33 +47,45 @@ fn read_ton_config_from_url_stream(url: Url, interval: Interval) -> impl Stream<
 }
 
 impl ClientDiscover {
-    pub(crate) async fn from_path(path: PathBuf, /* interval: Duration */) -> anyhow::Result<Self> {
+    pub(crate) async fn from_path(path: PathBuf, /* interval: Duration */ warm_up: bool) -> anyhow::Result<Self> {
         let mut interval = tokio::time::interval(Duration::from_secs(1));
         interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
         let stream = read_ton_config_from_file_stream(path, interval);
 
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-        tokio::spawn(Self::inner(tx, stream));
-
-        Ok(Self { rx })
+        Self::spawn(stream, warm_up).await
     }
 
-    pub(crate) async fn new(url: Url, period: Duration) -> anyhow::Result<Self> {
+    pub(crate) async fn new(url: Url, period: Duration, warm_up: bool) -> anyhow::Result<Self> {
         let mut interval = tokio::time::interval(period);
         interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
         let stream = read_ton_config_from_url_stream(url.clone(), interval);
 
+        Self::spawn(stream, warm_up).await
+    }
+
+    async fn spawn(stream: impl Stream<Item = Result<TonConfig, anyhow::Error>> + Send + 'static, warm_up: bool) -> anyhow::Result<Self> {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-        tokio::spawn(Self::inner(tx, stream));
+        let (warmed_up_tx, warmed_up_rx) = oneshot::channel();
+        tokio::spawn(Self::inner(tx, stream, warmed_up_tx));
+
+        if warm_up {
+            let connected = warmed_up_rx.await
+                .map_err(|_| anyhow::anyhow!("service discovery stopped before warm-up"))?;
+            if connected == 0 {
+                return Err(anyhow::anyhow!("warm-up failed: no liteserver is reachable"));
+            }
+
+            tracing::info!("warm-up finished, {} liteservers connected", connected);
+        }
 
         Ok(Self { rx })
     }
 
-    async fn inner(tx: UnboundedSender<DiscoverResult<Client>>, stream: impl Stream<Item = Result<TonConfig, anyhow::Error>>) {
+    async fn inner(tx: UnboundedSender<DiscoverResult<Client>>, stream: impl Stream<Item = Result<TonConfig, anyhow::Error>>, warmed_up: oneshot::Sender<usize>) {
         tokio::pin!(stream);
-        let mut factory = ClientFactory;
+        let mut warmed_up = Some(warmed_up);
         let mut liteservers = HashSet::default();
         let dns = Self::dns_resolver();
 
@@ -94,14 +111,28 @@ impl ClientDiscover {
                 let _ = tx.send(Ok(Change::Remove(ls.id())));
             }
 
-            for ls in liteserver_new.difference(&liteservers) {
+            let clients = join_all(liteserver_new.difference(&liteservers).map(|ls| {
                 tracing::info!("insert {:?}", ls.id());
 
-                if let Ok(client) = (&mut factory).oneshot(new_config.with_liteserver(ls)).await {
-                    let _ = tx.send(Ok(Change::Insert(ls.id(), client)));
+                let config = new_config.with_liteserver(ls);
+                async move { (ls.id(), ClientFactory.oneshot(config).await) }
+            })).await;
+
+            let mut connected = 0;
+            for (id, client) in clients {
+                match client {
+                    Ok(client) => {
+                        connected += 1;
+                        let _ = tx.send(Ok(Change::Insert(id, client)));
+                    },
+                    Err(e) => tracing::warn!("liteserver {:?} is unreachable: {:?}", id, e)
                 }
             }
 
+            if let Some(warmed_up) = warmed_up.take() {
+                let _ = warmed_up.send(connected);
+            }
+
             liteservers.clone_from(&liteserver_new);
         }
     }
@@ -141,12 +172,14 @@ impl Stream for ClientDiscover {
 }
 
 pub(crate) struct CursorClientDiscover {
-    discover: PeakEwmaDiscover<ClientDiscover>
+    discover: PeakEwmaDiscover<ClientDiscover>,
+    blacklist: Option<BlacklistPolicy>,
+    network_head: Arc<AtomicI32>
 }
 
 impl CursorClientDiscover {
-    pub(crate) fn new(discover: PeakEwmaDiscover<ClientDiscover>) -> Self {
-        Self { discover }
+    pub(crate) fn new(discover: PeakEwmaDiscover<ClientDiscover>, blacklist: Option<BlacklistPolicy>, network_head: Arc<AtomicI32>) -> Self {
+        Self { discover, blacklist, network_head }
     }
 }
 
@@ -154,11 +187,13 @@ impl Stream for CursorClientDiscover {
     type Item = DiscoverResult<CursorClient>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let blacklist = self.blacklist;
+        let network_head = self.network_head.clone();
         let c = &mut self.discover;
         match Pin::new(&mut *c).poll_next(cx) {
             Poll::Ready(Some(Ok(change))) => match change {
                 Change::Insert(k, client) => Poll::Ready(Some(Ok(
-                    Change::Insert(k.clone(), CursorClientFactory::create(k, client))
+                    Change::Insert(k.clone(), CursorClientFactory::create(k, client, blacklist, network_head))
                 ))),
                 Change::Remove(k) => Poll::Ready(Some(Ok(Change::Remove(k)))),
             },
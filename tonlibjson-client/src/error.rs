@@ -5,16 +5,47 @@ use futures::future::MapErr;
 use futures::TryFutureExt;
 use thiserror::Error;
 use tower::{Layer, Service};
+use tower::timeout::error::Elapsed;
 use ton_client_utils::router::RouterError;
+use crate::block::TonError;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error(transparent)]
     Router(#[from] RouterError),
+    #[error("liteserver {0} is not available")]
+    LiteserverUnavailable(String),
     #[error(transparent)]
     Custom(#[from] anyhow::Error)
 }
 
+impl Error {
+    /// True when no liteserver has the requested block, either because the router knows none covering it
+    /// or because the liteserver has already pruned it.
+    pub fn is_too_deep(e: &anyhow::Error) -> bool {
+        e.chain().any(|cause| {
+            matches!(cause.downcast_ref::<Error>(), Some(Error::Router(RouterError::RouteUnknown)))
+                || matches!(cause.downcast_ref::<RouterError>(), Some(RouterError::RouteUnknown))
+        }) || Self::ton_error(e).is_some_and(TonError::is_pruned)
+    }
+
+    /// True when the request timed out, either in the client stack or between tonlib and the liteserver.
+    pub fn is_timeout(e: &anyhow::Error) -> bool {
+        e.chain().any(|cause| match cause.downcast_ref::<Error>() {
+            Some(Error::Custom(inner)) => Self::is_timeout(inner),
+            _ => cause.is::<Elapsed>()
+        }) || Self::ton_error(e).is_some_and(TonError::is_timeout)
+    }
+
+    /// Finds the liteserver error behind `e`, looking through the layers of the client stack.
+    pub fn ton_error(e: &anyhow::Error) -> Option<&TonError> {
+        e.chain().find_map(|cause| match cause.downcast_ref::<Error>() {
+            Some(Error::Custom(inner)) => Self::ton_error(inner),
+            _ => cause.downcast_ref::<TonError>()
+        })
+    }
+}
+
 impl From<tower::BoxError> for Error {
     fn from(err: tower::BoxError) -> Self {
         Self::Custom(anyhow!(err))
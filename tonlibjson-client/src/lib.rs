@@ -7,15 +7,22 @@ mod session;
 mod ton_config;
 mod make;
 mod cursor_client;
-mod retry;
+pub mod retry;
 mod discover;
 mod client;
 mod balance;
-mod error;
+pub mod error;
 mod shared;
 mod deserialize;
 pub mod address;
+pub mod liteserver;
 mod helper;
 mod metric;
 mod router;
+pub mod cache;
+pub mod blacklist;
+pub mod lookback;
+pub mod debug_trace;
 pub mod dns_discover;
+
+pub use tonlibjson_sys::TONLIB_VERSION;
@@ -0,0 +1,121 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::error::Error;
+
+tokio::task_local! {
+    static SCOPE: LiteserverScope;
+}
+
+/// The liteserver a request is pinned to and the one that served it last.
+/// Cloned scopes share their state, so a streaming response can keep polling under the scope of its request.
+#[derive(Clone, Default)]
+pub struct LiteserverScope {
+    inner: Arc<Inner>
+}
+
+#[derive(Default)]
+struct Inner {
+    pinned: Option<String>,
+    unavailable: AtomicBool,
+    served_by: Mutex<Option<Cow<'static, str>>>
+}
+
+impl LiteserverScope {
+    pub fn pinned(id: String) -> Self {
+        Self { inner: Arc::new(Inner { pinned: Some(id), ..Default::default() }) }
+    }
+
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        SCOPE.scope(self.clone(), future).await
+    }
+
+    pub fn sync_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        SCOPE.sync_scope(self.clone(), f)
+    }
+
+    pub fn served_by(&self) -> Option<String> {
+        self.inner.served_by.lock().unwrap().as_ref().map(ToString::to_string)
+    }
+
+    /// Fails once a call had to be refused because the pinned liteserver was not available.
+    pub fn check(&self) -> Result<(), Error> {
+        match self.inner.pinned {
+            Some(ref id) if self.inner.unavailable.load(Ordering::Relaxed) => Err(Error::LiteserverUnavailable(id.clone())),
+            _ => Ok(())
+        }
+    }
+}
+
+pub(crate) fn record(id: &Cow<'static, str>) {
+    let _ = SCOPE.try_with(|scope| scope.inner.served_by.lock().unwrap().replace(id.clone()));
+}
+
+pub(crate) fn pinned() -> Option<String> {
+    SCOPE.try_with(|scope| scope.inner.pinned.clone()).ok().flatten()
+}
+
+pub(crate) fn mark_unavailable() {
+    let _ = SCOPE.try_with(|scope| scope.inner.unavailable.store(true, Ordering::Relaxed));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use crate::error::Error;
+    use crate::liteserver::{mark_unavailable, pinned, record, LiteserverScope};
+
+    #[tokio::test]
+    async fn scope_returns_last_recorded_liteserver() {
+        let scope = LiteserverScope::default();
+        let output = scope.scope(async {
+            record(&Cow::Borrowed("first"));
+            record(&Cow::Borrowed("second"));
+
+            42
+        }).await;
+
+        assert_eq!(output, 42);
+        assert_eq!(scope.served_by(), Some("second".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn record_outside_of_scope_is_ignored() {
+        record(&Cow::Borrowed("first"));
+
+        let scope = LiteserverScope::default();
+        scope.scope(async {}).await;
+
+        assert_eq!(scope.served_by(), None);
+    }
+
+    #[tokio::test]
+    async fn pinned_scope_exposes_pinned_liteserver() {
+        let scope = LiteserverScope::pinned("first".to_owned());
+        let output = scope.scope(async { pinned() }).await;
+
+        assert_eq!(output, Some("first".to_owned()));
+        assert_eq!(pinned(), None);
+    }
+
+    #[tokio::test]
+    async fn pinned_scope_fails_when_liteserver_is_unavailable() {
+        let scope = LiteserverScope::pinned("first".to_owned());
+        scope.scope(async { mark_unavailable() }).await;
+
+        assert!(matches!(scope.check(), Err(Error::LiteserverUnavailable(id)) if id == "first"));
+    }
+
+    #[test]
+    fn sync_scope_shares_state_with_its_clones() {
+        let scope = LiteserverScope::pinned("first".to_owned());
+
+        scope.clone().sync_scope(|| {
+            assert_eq!(pinned(), Some("first".to_owned()));
+            record(&Cow::Borrowed("first"));
+        });
+
+        assert_eq!(scope.served_by(), Some("first".to_owned()));
+    }
+}
@@ -0,0 +1,87 @@
+use async_stream::try_stream;
+use futures::{Stream, TryStreamExt};
+use thiserror::Error;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Lookback {
+    pub max_lt: Option<i64>,
+    pub max_count: Option<usize>
+}
+
+#[derive(Debug, Error)]
+#[error("lookback limit reached, stream is truncated")]
+pub struct LookbackExceeded;
+
+impl Lookback {
+    pub fn limit<T, S>(self, stream: S, lt: impl Fn(&T) -> i64 + Send + 'static) -> impl Stream<Item = anyhow::Result<T>> + Send + 'static
+        where T: Send + 'static,
+              S: Stream<Item = anyhow::Result<T>> + Send + 'static {
+        try_stream! {
+            tokio::pin!(stream);
+
+            let mut first_lt = None;
+            let mut count = 0;
+            while let Some(item) = stream.try_next().await? {
+                let first_lt = *first_lt.get_or_insert(lt(&item));
+
+                let lt_exceeded = self.max_lt.is_some_and(|max_lt| (first_lt - lt(&item)).abs() > max_lt);
+                let count_exceeded = self.max_count.is_some_and(|max_count| count >= max_count);
+                if lt_exceeded || count_exceeded {
+                    Err(LookbackExceeded)?;
+                }
+
+                count += 1;
+                yield item;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{stream, StreamExt};
+    use crate::lookback::{Lookback, LookbackExceeded};
+
+    async fn collect(lookback: Lookback, lts: Vec<i64>) -> (Vec<i64>, bool) {
+        let items: Vec<_> = lookback.limit(stream::iter(lts.into_iter().map(anyhow::Ok)), |lt| *lt)
+            .collect()
+            .await;
+
+        let truncated = items.last().is_some_and(|item| item.as_ref().is_err_and(|e| e.is::<LookbackExceeded>()));
+        let items = items.into_iter().filter_map(Result::ok).collect();
+
+        (items, truncated)
+    }
+
+    #[tokio::test]
+    async fn unbounded_lookback_yields_everything() {
+        let (items, truncated) = collect(Lookback::default(), vec![30, 20, 10]).await;
+
+        assert_eq!(items, vec![30, 20, 10]);
+        assert!(!truncated);
+    }
+
+    #[tokio::test]
+    async fn max_count_truncates_stream() {
+        let (items, truncated) = collect(Lookback { max_count: Some(2), ..Default::default() }, vec![30, 20, 10]).await;
+
+        assert_eq!(items, vec![30, 20]);
+        assert!(truncated);
+    }
+
+    #[tokio::test]
+    async fn max_count_does_not_truncate_exhausted_stream() {
+        let (items, truncated) = collect(Lookback { max_count: Some(3), ..Default::default() }, vec![30, 20, 10]).await;
+
+        assert_eq!(items, vec![30, 20, 10]);
+        assert!(!truncated);
+    }
+
+    #[tokio::test]
+    async fn max_lt_truncates_stream() {
+        let (items, truncated) = collect(Lookback { max_lt: Some(15), ..Default::default() }, vec![30, 20, 10]).await;
+
+        assert_eq!(items, vec![30, 20]);
+        assert!(truncated);
+    }
+}
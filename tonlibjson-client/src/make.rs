@@ -1,11 +1,14 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::AtomicI32;
 use std::task::{Context, Poll};
 use serde_json::{json, Value};
 use tower::limit::ConcurrencyLimitLayer;
 use tower::{Layer, Service, ServiceExt};
 use tower::load::PeakEwma;
 use tracing::debug;
+use crate::blacklist::BlacklistPolicy;
 use crate::block::BlocksGetMasterchainInfo;
 use crate::client::Client;
 use crate::cursor_client::CursorClient;
@@ -42,14 +45,14 @@ impl Service<TonConfig> for ClientFactory {
 pub(crate) struct CursorClientFactory;
 
 impl CursorClientFactory {
-    pub(crate) fn create(id: String, client: PeakEwma<Client>) -> CursorClient {
+    pub(crate) fn create(id: String, client: PeakEwma<Client>, blacklist: Option<BlacklistPolicy>, network_head: Arc<AtomicI32>) -> CursorClient {
         debug!("make new cursor client");
         let client = SharedLayer
             .layer(client);
         let client = ConcurrencyLimitLayer::new(256)
             .layer(client);
 
-        let client = CursorClient::new(id, client);
+        let client = CursorClient::new(id, client, blacklist, network_head);
 
         debug!("successfully made new cursor client");
 
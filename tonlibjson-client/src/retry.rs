@@ -1,5 +1,6 @@
-use std::sync::Arc;
-use std::time::Duration;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use futures::future::BoxFuture;
 use futures::FutureExt;
 use tower::retry::budget::Budget;
@@ -9,25 +10,188 @@ use ton_client_utils::router::RouterError;
 use crate::block::{RawSendMessage, RawSendMessageReturnHash};
 use crate::error::Error;
 
+const BUDGET_METER_SLOTS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RetryBackoff {
+    Exponential,
+    Constant,
+    #[default]
+    Fibonacci
+}
+
+impl FromStr for RetryBackoff {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exponential" => Ok(Self::Exponential),
+            "constant" => Ok(Self::Constant),
+            "fibonacci" => Ok(Self::Fibonacci),
+            _ => Err(anyhow::anyhow!("unknown retry backoff {}, expected exponential, constant or fibonacci", s))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryConfig {
+    pub budget_ttl: Duration,
+    pub min_per_sec: u32,
+    pub retry_percent: f32,
+    pub first_delay: Duration,
+    pub max_delay: Duration,
+    pub backoff: RetryBackoff
+}
+
+struct RetryState {
+    config: RetryConfig,
+    budget: Arc<Budget>,
+    meter: Arc<BudgetMeter>
+}
+
+impl RetryState {
+    fn new(config: RetryConfig) -> Self {
+        Self {
+            config,
+            budget: Arc::new(Budget::new(config.budget_ttl, config.min_per_sec, config.retry_percent)),
+            meter: Arc::new(BudgetMeter::new(config.budget_ttl, config.min_per_sec, config.retry_percent))
+        }
+    }
+
+    /// Keeps the budget and its balance unless one of its parameters changed.
+    fn reload(&self, config: RetryConfig) -> Self {
+        let current = &self.config;
+        if (current.budget_ttl, current.min_per_sec, current.retry_percent) != (config.budget_ttl, config.min_per_sec, config.retry_percent) {
+            return Self::new(config)
+        }
+
+        Self { config, budget: self.budget.clone(), meter: self.meter.clone() }
+    }
+
+    fn delay(&self, attempt: usize) -> Duration {
+        let first_delay = self.config.first_delay;
+        let delay = match self.config.backoff {
+            RetryBackoff::Constant => first_delay,
+            RetryBackoff::Exponential => first_delay.saturating_mul(2_u32.saturating_pow(attempt as u32)),
+            RetryBackoff::Fibonacci => FibonacciBackoff::from_millis(first_delay.as_millis() as u64)
+                .max_delay(self.config.max_delay)
+                .nth(attempt)
+                .unwrap()
+        };
+
+        jitter(delay.min(self.config.max_delay))
+    }
+}
+
+#[derive(Clone)]
+pub struct RetryHandle {
+    state: Arc<RwLock<Arc<RetryState>>>
+}
+
+impl RetryHandle {
+    fn new(config: RetryConfig) -> Self {
+        Self { state: Arc::new(RwLock::new(Arc::new(RetryState::new(config)))) }
+    }
+
+    pub fn config(&self) -> RetryConfig {
+        self.current().config
+    }
+
+    pub fn reload(&self, config: RetryConfig) {
+        let mut state = self.state.write().unwrap();
+        *state = Arc::new(state.reload(config));
+
+        tracing::info!(config = ?config, "retry config reloaded");
+    }
+
+    fn current(&self) -> Arc<RetryState> {
+        self.state.read().unwrap().clone()
+    }
+}
+
 #[derive(Clone)]
 pub struct RetryPolicy {
-    budget: Arc<Budget>,
-    backoff: FibonacciBackoff
+    handle: RetryHandle,
+    attempt: usize
 }
 
 impl RetryPolicy {
-    pub fn new(budget: Budget, first_delay_millis: u64, max_delay: Duration) -> Self {
+    pub fn new(config: RetryConfig) -> Self {
         metrics::describe_counter!("ton_retry_budget_withdraw_success", "Number of withdraws that were successful");
         metrics::describe_counter!("ton_retry_budget_withdraw_fail", "Number of withdraws that were unsuccessful");
+        metrics::describe_gauge!("ton_retry_budget_balance", "Estimated number of retries the budget allows right now");
+
+        Self { handle: RetryHandle::new(config), attempt: 0 }
+    }
+
+    pub fn handle(&self) -> RetryHandle {
+        self.handle.clone()
+    }
+}
+
+// tower's Budget doesn't expose its balance, so deposits and withdrawals are mirrored
+// into the same sliding window to estimate how many retries are currently left.
+struct BudgetMeter {
+    reserve: f64,
+    deposit_amount: f64,
+    slot: Duration,
+    state: Mutex<BudgetMeterState>
+}
 
-        let retry_strategy = FibonacciBackoff::from_millis(first_delay_millis)
-            .max_delay(max_delay);
+struct BudgetMeterState {
+    rotated_at: Instant,
+    index: usize,
+    slots: [f64; BUDGET_METER_SLOTS]
+}
 
+impl BudgetMeter {
+    fn new(ttl: Duration, min_per_sec: u32, retry_percent: f32) -> Self {
         Self {
-            budget: Arc::new(budget),
-            backoff: retry_strategy
+            reserve: min_per_sec as f64 * ttl.as_secs_f64(),
+            deposit_amount: retry_percent as f64,
+            slot: ttl / BUDGET_METER_SLOTS as u32,
+            state: Mutex::new(BudgetMeterState { rotated_at: Instant::now(), index: 0, slots: [0.0; BUDGET_METER_SLOTS] })
         }
     }
+
+    fn deposit(&self) {
+        self.record(self.deposit_amount);
+    }
+
+    fn withdraw(&self) {
+        self.record(-1.0);
+    }
+
+    fn record(&self, amount: f64) {
+        let balance = {
+            let mut state = self.state.lock().unwrap();
+
+            let elapsed = state.rotated_at.elapsed();
+            if elapsed >= self.slot {
+                let rotations = (elapsed.as_nanos() / self.slot.as_nanos().max(1)).min(BUDGET_METER_SLOTS as u128);
+                for _ in 0..rotations {
+                    state.index = (state.index + 1) % BUDGET_METER_SLOTS;
+                    let index = state.index;
+                    state.slots[index] = 0.0;
+                }
+                state.rotated_at = Instant::now();
+            }
+
+            let index = state.index;
+            state.slots[index] += amount;
+
+            self.reserve + state.slots.iter().sum::<f64>()
+        };
+
+        metrics::gauge!("ton_retry_budget_balance").set(balance.max(0.0));
+    }
+
+    #[cfg(test)]
+    fn balance(&self) -> f64 {
+        let state = self.state.lock().unwrap();
+
+        (self.reserve + state.slots.iter().sum::<f64>()).max(0.0)
+    }
 }
 
 impl<Res, E> Policy<RawSendMessageReturnHash, Res, E> for RetryPolicy {
@@ -50,31 +214,32 @@ impl<T: Clone, Res> Policy<T, Res, Error> for RetryPolicy {
     type Future = BoxFuture<'static, Self>;
 
     fn retry(&self, _: &T, result: Result<&Res, &Error>) -> Option<Self::Future> {
+        let state = self.handle.current();
+
         match result {
             Ok(_) => {
-                self.budget.deposit();
+                state.budget.deposit();
+                state.meter.deposit();
 
                 None
             },
             Err(Error::Router(RouterError::RouteUnknown)) => None,
+            Err(Error::LiteserverUnavailable(_)) => None,
             Err(_) => {
                 let request_type: &str = std::any::type_name::<T>();
 
-                match self.budget.withdraw() {
+                match state.budget.withdraw() {
                     Ok(_) => {
+                        state.meter.withdraw();
                         metrics::counter!("ton_retry_budget_withdraw_success", "request_type" => request_type).increment(1);
 
                         Some({
                             let mut pol = self.clone();
+                            let delay = state.delay(pol.attempt);
+                            pol.attempt += 1;
 
                             async move {
-                                let millis = pol.backoff
-                                    .by_ref()
-                                    .map(jitter)
-                                    .next()
-                                    .unwrap();
-
-                                tokio::time::sleep(millis).await;
+                                tokio::time::sleep(delay).await;
 
                                 pol
                             }.boxed()
@@ -93,3 +258,85 @@ impl<T: Clone, Res> Policy<T, Res, Error> for RetryPolicy {
         Some(req.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::retry::{BudgetMeter, RetryBackoff, RetryConfig, RetryPolicy};
+
+    #[test]
+    fn reload_replaces_config() {
+        let config = RetryConfig {
+            budget_ttl: Duration::from_secs(10),
+            min_per_sec: 10,
+            retry_percent: 0.1,
+            first_delay: Duration::from_millis(128),
+            max_delay: Duration::from_millis(4096),
+            backoff: RetryBackoff::Fibonacci
+        };
+        let handle = RetryPolicy::new(config).handle();
+
+        handle.reload(RetryConfig { min_per_sec: 1, max_delay: Duration::from_millis(256), ..config });
+
+        assert_eq!(handle.config().min_per_sec, 1);
+        assert_eq!(handle.config().max_delay, Duration::from_millis(256));
+        assert!(handle.current().delay(10) <= Duration::from_millis(256));
+    }
+
+    #[test]
+    fn reload_keeps_unchanged_budget() {
+        let config = RetryConfig {
+            budget_ttl: Duration::from_secs(10),
+            min_per_sec: 10,
+            retry_percent: 0.1,
+            first_delay: Duration::from_millis(128),
+            max_delay: Duration::from_millis(4096),
+            backoff: RetryBackoff::Fibonacci
+        };
+        let handle = RetryPolicy::new(config).handle();
+        let budget = handle.current().budget.clone();
+
+        handle.reload(RetryConfig { max_delay: Duration::from_millis(256), ..config });
+        assert!(Arc::ptr_eq(&budget, &handle.current().budget));
+
+        handle.reload(RetryConfig { min_per_sec: 1, ..config });
+        assert!(!Arc::ptr_eq(&budget, &handle.current().budget));
+    }
+
+    #[test]
+    fn delay_follows_backoff() {
+        let config = RetryConfig {
+            budget_ttl: Duration::from_secs(10),
+            min_per_sec: 10,
+            retry_percent: 0.1,
+            first_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            backoff: RetryBackoff::Constant
+        };
+        let handle = RetryPolicy::new(config).handle();
+        assert!(handle.current().delay(5) <= Duration::from_millis(100));
+
+        handle.reload(RetryConfig { backoff: RetryBackoff::Exponential, ..config });
+        assert!(handle.current().delay(2) <= Duration::from_millis(400));
+        assert!(handle.current().delay(64) <= Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn budget_meter_tracks_deposits_and_withdrawals() {
+        let meter = BudgetMeter::new(Duration::from_secs(10), 1, 0.5);
+        assert_eq!(meter.balance(), 10.0);
+
+        meter.deposit();
+        meter.deposit();
+        meter.withdraw();
+
+        assert_eq!(meter.balance(), 10.0);
+
+        for _ in 0..20 {
+            meter.withdraw();
+        }
+
+        assert_eq!(meter.balance(), 0.0);
+    }
+}
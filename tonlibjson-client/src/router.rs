@@ -1,3 +1,4 @@
+use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::future::{Ready, ready};
 use std::hash::Hash;
@@ -8,6 +9,7 @@ use tower::discover::{Change, Discover, ServiceList};
 use tower::Service;
 use ton_client_utils::router::{Route, Routed, RouterError};
 use crate::error::{Error, ErrorService};
+use crate::liteserver;
 
 pub(crate) trait Routable {
     fn route(&self) -> Route { Route::Latest }
@@ -58,7 +60,7 @@ impl<S, D, Request> Service<&Request> for Router<S, D>
         S: Service<Request> + Routed + Clone,
         S::Error: Into<tower::BoxError>,
         D: Discover<Service=S, Error = anyhow::Error> + Unpin,
-        D::Key: Hash
+        D::Key: Hash + Eq + Borrow<str>
 {
     type Response = ErrorService<Balance<ServiceList<Vec<S>>, Request>>;
     type Error = Error;
@@ -80,6 +82,19 @@ impl<S, D, Request> Service<&Request> for Router<S, D>
     }
 
     fn call(&mut self, req: &Request) -> Self::Future {
+        if let Some(id) = liteserver::pinned() {
+            return ready(match self.services.get(id.as_str()) {
+                Some(service) if service.last_seqno().is_some() => Ok(
+                    ErrorService::new(Balance::new(ServiceList::new(vec![service.clone()])))
+                ),
+                _ => {
+                    liteserver::mark_unavailable();
+
+                    Err(Error::LiteserverUnavailable(id))
+                }
+            })
+        }
+
         ready(match req.route().choose(self.services.values()) {
             Ok(services) => Ok(
                 ErrorService::new(Balance::new(ServiceList::new(
@@ -3,8 +3,8 @@ use futures::future::BoxFuture;
 use futures::TryFutureExt;
 use futures::FutureExt;
 use tower::{Service, ServiceExt};
-use ton_client_utils::router::Route;
-use crate::block::{AccountAddress, SmcBoxedMethodId, SmcLoad, SmcRunGetMethod, TvmBoxedStackEntry};
+use ton_client_utils::router::{BlockCriteria, Route};
+use crate::block::{AccountAddress, QueryEstimateFees, RawCreateQuery, SmcBoxedMethodId, SmcLoad, SmcRunGetMethod, TonBlockIdExt, TvmBoxedStackEntry, WithBlock};
 use crate::error::Error;
 use crate::request::{Requestable, Callable};
 use crate::router::Routable;
@@ -13,12 +13,15 @@ use crate::router::Routable;
 pub struct RunGetMethod {
     address: AccountAddress,
     method: SmcBoxedMethodId,
-    stack: Vec<TvmBoxedStackEntry>
+    stack: Vec<TvmBoxedStackEntry>,
+    block: Option<TonBlockIdExt>
 }
 
 impl<S, E: Into<Error> + Send + 'static> Callable<S> for RunGetMethod
     where S: Service<SmcLoad, Response=<SmcLoad as Requestable>::Response, Error=E>,
           <S as Service<SmcLoad>>::Future: Send,
+          S: Service<WithBlock<SmcLoad>, Response=<SmcLoad as Requestable>::Response, Error=E>,
+          <S as Service<WithBlock<SmcLoad>>>::Future: Send,
           S: Service<SmcRunGetMethod, Response=<SmcRunGetMethod as Requestable>::Response, Error=E>,
           <S as Service<SmcRunGetMethod>>::Future: Send,
           S: Send + Clone + 'static {
@@ -29,8 +32,12 @@ impl<S, E: Into<Error> + Send + 'static> Callable<S> for RunGetMethod
     fn call(self, client: &mut S) -> Self::Future {
         let clone = client.clone();
 
-        client.call(SmcLoad::new(self.address))
-            .map_err(Into::into)
+        let load = match self.block {
+            Some(block) => client.call(WithBlock::new(block, SmcLoad::new(self.address))).boxed(),
+            None => client.call(SmcLoad::new(self.address)).boxed()
+        };
+
+        load.map_err(Into::into)
             .and_then(move |info| {
             clone
                 .oneshot(SmcRunGetMethod::new(info.id, self.method, self.stack))
@@ -40,5 +47,43 @@ impl<S, E: Into<Error> + Send + 'static> Callable<S> for RunGetMethod
 }
 
 impl Routable for RunGetMethod {
+    fn route(&self) -> Route {
+        match &self.block {
+            Some(block) => Route::Block { chain: block.workchain, criteria: BlockCriteria::Seqno { shard: block.shard, seqno: block.seqno } },
+            None => Route::Latest
+        }
+    }
+}
+
+#[derive(new, Clone)]
+pub struct EstimateFee {
+    query: RawCreateQuery,
+    ignore_chksig: bool
+}
+
+impl<S, E: Into<Error> + Send + 'static> Callable<S> for EstimateFee
+    where S: Service<RawCreateQuery, Response=<RawCreateQuery as Requestable>::Response, Error=E>,
+          <S as Service<RawCreateQuery>>::Future: Send,
+          S: Service<QueryEstimateFees, Response=<QueryEstimateFees as Requestable>::Response, Error=E>,
+          <S as Service<QueryEstimateFees>>::Future: Send,
+          S: Send + Clone + 'static {
+    type Response = <QueryEstimateFees as Requestable>::Response;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn call(self, client: &mut S) -> Self::Future {
+        let clone = client.clone();
+
+        client.call(self.query)
+            .map_err(Into::into)
+            .and_then(move |info| {
+                clone
+                    .oneshot(QueryEstimateFees::new(info.id, self.ignore_chksig))
+                    .map_err(Into::into)
+            }).boxed()
+    }
+}
+
+impl Routable for EstimateFee {
     fn route(&self) -> Route { Route::Latest }
 }
@@ -1,18 +1,22 @@
 use std::cmp::min;
 use std::collections::{Bound, HashMap};
-use std::future::IntoFuture;
+use std::future::{Future, IntoFuture};
 use std::ops::{RangeBounds};
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use futures::{Stream, stream, TryStreamExt, StreamExt, try_join, TryStream, TryFutureExt, FutureExt};
 use anyhow::anyhow;
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE, URL_SAFE_NO_PAD}, Engine};
+use toner::tlb::bits::de::unpack_bytes;
+use toner::ton::boc::BoC;
 use async_stream::try_stream;
 use futures::future::BoxFuture;
 use itertools::Itertools;
 use serde_json::Value;
 use tokio_stream::StreamMap;
 use tower::load::PeakEwmaDiscover;
-use tower::retry::budget::Budget;
 use tower::retry::Retry;
 use tower::{Layer, ServiceExt};
 use tower::timeout::Timeout;
@@ -21,18 +25,28 @@ use url::Url;
 use std::str::FromStr;
 use tower::util::Either;
 use ton_client_utils::router::{BlockCriteria, Route};
-use crate::address::InternalAccountAddress;
+use crate::address::{AccountAddressData, InternalAccountAddress};
 use crate::balance::Balance;
+use crate::blacklist::BlacklistPolicy;
+use crate::cache::{CacheControl, CacheStats, SingleFlightCache, TtlCache};
 use crate::router::Router;
-use crate::block::{InternalTransactionId, RawTransaction, RawTransactions, BlocksShards, BlocksTransactions, RawSendMessage, AccountAddress, BlocksGetTransactions, BlocksLookupBlock, BlocksGetShards, BlocksGetBlockHeader, RawGetTransactionsV2, RawGetAccountState, GetAccountState, GetShardAccountCell, RawFullAccountState, WithBlock, RawGetAccountStateByTransaction, GetShardAccountCellByTransaction, RawSendMessageReturnHash, BlocksMasterchainInfo, BlocksGetMasterchainInfo, TonBlockIdExt, TonBlockId, BlocksHeader, FullAccountState, BlocksAccountTransactionId, BlocksShortTxId, TvmBoxedStackEntry, SmcRunResult, SmcBoxedMethodId, TvmCell, BlocksGetTransactionsExt, BlocksTransactionsExt};
+use crate::block::{InternalTransactionId, RawTransaction, RawTransactions, BlocksShards, BlocksTransactions, RawSendMessage, AccountAddress, BlocksGetTransactions, BlocksLookupBlock, BlocksGetShards, BlocksGetBlockHeader, BlocksGetShardBlockProof, BlocksShardBlockProof, BlocksGetMasterchainBlockSignatures, BlocksBlockSignatures, RawGetTransactionsV2, RawGetAccountState, GetAccountState, GetShardAccountCell, RawFullAccountState, WithBlock, RawGetAccountStateByTransaction, GetShardAccountCellByTransaction, RawSendMessageReturnHash, BlocksMasterchainInfo, BlocksGetMasterchainInfo, TonBlockIdExt, TonBlockId, BlocksHeader, FullAccountState, BlocksAccountTransactionId, BlocksShortTxId, TvmBoxedStackEntry, SmcRunResult, SmcBoxedMethodId, TvmCell, BlocksGetTransactionsExt, BlocksTransactionsExt, GetConfigParam, QueryFees, RawCreateQuery};
 use crate::discover::{ClientDiscover, CursorClientDiscover};
-use crate::error::ErrorService;
+use crate::error::{Error, ErrorService};
 use crate::helper::Side;
 use crate::request::{Forward, Specialized};
-use crate::retry::RetryPolicy;
-use crate::session::RunGetMethod;
+use crate::retry::{RetryBackoff, RetryConfig, RetryHandle, RetryPolicy};
+use crate::session::{EstimateFee, RunGetMethod};
 use crate::shared::SharedService;
 
+#[derive(Debug, thiserror::Error)]
+#[error("idempotency key was already used for a different message")]
+pub struct IdempotencyKeyReused;
+
+#[derive(Debug, thiserror::Error)]
+#[error("more than {0} key blocks lie between the blocks")]
+pub struct TooManyKeyBlocks(pub usize);
+
 #[cfg(not(feature = "testnet"))]
 pub fn default_ton_config_url() -> Url {
     Url::from_str("https://raw.githubusercontent.com/ton-blockchain/ton-blockchain.github.io/main/global.config.json").unwrap()
@@ -44,14 +58,51 @@ pub fn default_ton_config_url() -> Url {
 }
 
 type SharedBalance = SharedService<Balance<CursorClientDiscover>>;
+type ArchivalClient = ErrorService<Timeout<SharedBalance>>;
 
 #[derive(Clone)]
 pub struct TonClient {
-    client: ErrorService<Timeout<Either<Retry<RetryPolicy, SharedBalance>, SharedBalance>>>
+    client: ErrorService<Timeout<Either<Retry<RetryPolicy, SharedBalance>, SharedBalance>>>,
+    archival: Option<ArchivalClient>,
+    raw_account_state_cache: Option<Arc<TtlCache<String, RawFullAccountState>>>,
+    account_state_cache: Option<Arc<TtlCache<String, FullAccountState>>>,
+    key_block_seqno_cache: Option<Arc<TtlCache<i32, i32>>>,
+    config_cache: Option<Arc<TtlCache<(i32, i32), TvmCell>>>,
+    get_method_cache: Option<Arc<TtlCache<(String, String, String, i64), SmcRunResult>>>,
+    shards_cache: Option<Arc<TtlCache<TonBlockIdExt, BlocksShards>>>,
+    send_message_dedup: Option<Arc<TtlCache<String, String>>>,
+    idempotency_keys: Option<Arc<TtlCache<String, (String, Arc<tokio::sync::OnceCell<String>>)>>>,
+    masterchain_info_cache: Option<Arc<SingleFlightCache<BlocksMasterchainInfo>>>,
+    global_id: Arc<tokio::sync::OnceCell<i32>>,
+    synced: Arc<tokio::sync::watch::Sender<bool>>,
+    network_head: Arc<AtomicI32>,
+    retry: Option<RetryHandle>
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockProof {
+    pub proof: BlocksShardBlockProof,
+    pub signatures: Vec<BlocksBlockSignatures>
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncStatus {
+    pub synced: bool,
+    pub current_seqno: Option<i32>,
+    pub target_seqno: Option<i32>,
+    pub lag: Option<Duration>
 }
 
 const MAIN_CHAIN: i32 = -1;
 const MAIN_SHARD: i64 = -9223372036854775808;
+const SYNC_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Blocks of the destination shard scanned for the result transaction of a message.
+const TRY_LOCATE_MAX_BLOCKS: usize = 16;
+/// Key blocks a forward block proof may span, each one costs a header and a signatures request.
+const BLOCK_PROOF_MAX_KEY_BLOCKS: usize = 16;
+/// Consecutive failed checks before the client counts as out of sync, so one slow answer doesn't fail every request.
+const SYNC_CHECK_FAILURES: usize = 3;
+const SYNC_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
 
 enum ConfigSource {
     FromFile { path: PathBuf },
@@ -60,6 +111,7 @@ enum ConfigSource {
 
 pub struct TonClientBuilder {
     config_source: ConfigSource,
+    archival_config_url: Option<Url>,
     timeout: Duration,
     ewma_default_rtt: Duration,
     ewma_decay: Duration,
@@ -68,13 +120,24 @@ pub struct TonClientBuilder {
     retry_min_per_sec: u32,
     retry_percent: f32,
     retry_first_delay: Duration,
-    retry_max_delay: Duration
+    retry_max_delay: Duration,
+    retry_backoff: RetryBackoff,
+    account_state_cache: Option<(Duration, usize)>,
+    config_cache: Option<(Duration, usize)>,
+    get_method_cache: Option<(Duration, usize)>,
+    shards_cache: Option<(Duration, usize)>,
+    send_message_dedup: Option<(Duration, usize)>,
+    idempotency_keys: Option<(Duration, usize)>,
+    masterchain_info_cache: Option<Duration>,
+    liteserver_blacklist: Option<BlacklistPolicy>,
+    warm_up: bool
 }
 
 impl Default for TonClientBuilder {
     fn default() -> Self {
         Self {
             config_source: ConfigSource::FromUrl { url: default_ton_config_url(), interval: Duration::from_secs(60) },
+            archival_config_url: None,
             timeout: Duration::from_secs(10),
             ewma_default_rtt: Duration::from_millis(70),
             ewma_decay: Duration::from_millis(1),
@@ -83,7 +146,17 @@ impl Default for TonClientBuilder {
             retry_min_per_sec: 10,
             retry_percent: 0.1,
             retry_first_delay: Duration::from_millis(128),
-            retry_max_delay: Duration::from_millis(4096)
+            retry_max_delay: Duration::from_millis(4096),
+            retry_backoff: RetryBackoff::default(),
+            account_state_cache: None,
+            config_cache: None,
+            get_method_cache: None,
+            shards_cache: None,
+            send_message_dedup: None,
+            idempotency_keys: None,
+            masterchain_info_cache: None,
+            liteserver_blacklist: None,
+            warm_up: false
         }
     }
 }
@@ -103,6 +176,13 @@ impl TonClientBuilder {
         }
     }
 
+    /// getTransactions requests that reach past the history of the main liteservers are retried against these.
+    pub fn set_archival_config_url(mut self, url: Url) -> Self {
+        self.archival_config_url = Some(url);
+
+        self
+    }
+
     pub fn set_ewma_default_rtt(mut self, default_rtt: Duration) -> Self {
         self.ewma_default_rtt = default_rtt;
 
@@ -151,18 +231,73 @@ impl TonClientBuilder {
         self
     }
 
+    pub fn set_retry_backoff(mut self, backoff: RetryBackoff) -> Self {
+        self.retry_backoff = backoff;
+
+        self
+    }
+
     pub fn set_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
 
         self
     }
 
-    pub async fn build(self) -> anyhow::Result<TonClient> {
-        let client_discover = match self.config_source {
-            ConfigSource::FromFile { path } => { ClientDiscover::from_path(path).await? }
-            ConfigSource::FromUrl { url, interval } => { ClientDiscover::new(url, interval).await? }
-        };
+    pub fn set_account_state_cache(mut self, ttl: Duration, capacity: usize) -> Self {
+        self.account_state_cache = Some((ttl, capacity));
+
+        self
+    }
+
+    pub fn set_config_cache(mut self, ttl: Duration, capacity: usize) -> Self {
+        self.config_cache = Some((ttl, capacity));
+
+        self
+    }
+
+    pub fn set_get_method_cache(mut self, ttl: Duration, capacity: usize) -> Self {
+        self.get_method_cache = Some((ttl, capacity));
+
+        self
+    }
+
+    pub fn set_shards_cache(mut self, ttl: Duration, capacity: usize) -> Self {
+        self.shards_cache = Some((ttl, capacity));
+
+        self
+    }
+
+    pub fn set_send_message_dedup(mut self, ttl: Duration, capacity: usize) -> Self {
+        self.send_message_dedup = Some((ttl, capacity));
+
+        self
+    }
+
+    pub fn set_idempotency_keys(mut self, ttl: Duration, capacity: usize) -> Self {
+        self.idempotency_keys = Some((ttl, capacity));
+
+        self
+    }
+
+    pub fn set_masterchain_info_cache(mut self, ttl: Duration) -> Self {
+        self.masterchain_info_cache = Some(ttl);
 
+        self
+    }
+
+    pub fn set_liteserver_blacklist(mut self, max_failures: usize, window: Duration) -> Self {
+        self.liteserver_blacklist = Some(BlacklistPolicy::new(max_failures, window));
+
+        self
+    }
+
+    pub fn warm_up(mut self) -> Self {
+        self.warm_up = true;
+
+        self
+    }
+
+    fn balance(&self, client_discover: ClientDiscover, network_head: Arc<AtomicI32>) -> SharedBalance {
         let ewma_discover = PeakEwmaDiscover::new::<Value>(
             client_discover,
             self.ewma_default_rtt,
@@ -170,24 +305,128 @@ impl TonClientBuilder {
             tower::load::CompleteOnResponse::default(),
         );
 
-        let cursor_client_discover = CursorClientDiscover::new(ewma_discover);
-
+        let cursor_client_discover = CursorClientDiscover::new(ewma_discover, self.liteserver_blacklist, network_head);
         let router = Router::new(cursor_client_discover);
-        let client = Balance::new(router);
 
-        let client = SharedService::new(client);
-        let client = tower::util::option_layer(if self.retry_enabled {
-            Some(tower::retry::RetryLayer::new(RetryPolicy::new(Budget::new(
-                self.retry_budget_ttl,
-                self.retry_min_per_sec,
-                self.retry_percent
-            ), self.retry_first_delay.as_millis() as u64, self.retry_max_delay)))
-        } else { None }).layer(client);
+        SharedService::new(Balance::new(router))
+    }
+
+    pub async fn build(self) -> anyhow::Result<TonClient> {
+        let client_discover = match &self.config_source {
+            ConfigSource::FromFile { path } => { ClientDiscover::from_path(path.clone(), self.warm_up).await? }
+            ConfigSource::FromUrl { url, interval } => { ClientDiscover::new(url.clone(), *interval, self.warm_up).await? }
+        };
+
+        let network_head = Arc::new(AtomicI32::new(0));
+        let client = self.balance(client_discover, network_head.clone());
+
+        let archival = match self.archival_config_url.clone() {
+            Some(url) => {
+                metrics::describe_counter!("ton_archival_escalations_total", "Count of requests retried against archival liteservers");
+
+                let client_discover = ClientDiscover::new(url, Duration::from_secs(60), self.warm_up).await?;
+
+                Some(ErrorService::new(Timeout::new(self.balance(client_discover, Default::default()), self.timeout)))
+            },
+            None => None
+        };
+        let retry = self.retry_enabled.then(|| RetryPolicy::new(RetryConfig {
+            budget_ttl: self.retry_budget_ttl,
+            min_per_sec: self.retry_min_per_sec,
+            retry_percent: self.retry_percent,
+            first_delay: self.retry_first_delay,
+            max_delay: self.retry_max_delay,
+            backoff: self.retry_backoff
+        }));
+        let retry_handle = retry.as_ref().map(RetryPolicy::handle);
+        let client = tower::util::option_layer(retry.map(tower::retry::RetryLayer::new)).layer(client);
 
         let client = Timeout::new(client, self.timeout);
         let client = ErrorService::new(client);
 
-        Ok(TonClient { client } )
+        let synced = Arc::new(tokio::sync::watch::Sender::new(false));
+        tokio::spawn({
+            let client = client.clone();
+            let synced = Arc::downgrade(&synced);
+
+            async move {
+                let mut failures = 0;
+                loop {
+                    let request = client.clone().oneshot(Specialized::new(BlocksGetMasterchainInfo::default()));
+                    let is_synced = tokio::time::timeout(SYNC_CHECK_TIMEOUT, request).await
+                        .is_ok_and(|response| response.is_ok());
+
+                    // the client is gone
+                    let Some(synced) = synced.upgrade() else { break };
+
+                    failures = if is_synced { 0 } else { failures + 1 };
+                    if is_synced || failures >= SYNC_CHECK_FAILURES {
+                        synced.send_replace(is_synced);
+                    }
+                    drop(synced);
+
+                    tokio::time::sleep(SYNC_CHECK_INTERVAL).await;
+                }
+            }
+        });
+
+        let (raw_account_state_cache, account_state_cache) = match self.account_state_cache {
+            Some((ttl, capacity)) => (
+                Some(Arc::new(TtlCache::new("raw_account_state", ttl, capacity))),
+                Some(Arc::new(TtlCache::new("account_state", ttl, capacity)))
+            ),
+            None => (None, None)
+        };
+
+        let (key_block_seqno_cache, config_cache) = match self.config_cache {
+            Some((ttl, capacity)) => (
+                Some(Arc::new(TtlCache::new("key_block_seqno", ttl, capacity))),
+                Some(Arc::new(TtlCache::new("config", ttl, capacity)))
+            ),
+            None => (None, None)
+        };
+
+        let get_method_cache = self.get_method_cache.map(|(ttl, capacity)| {
+            Arc::new(TtlCache::new("get_method", ttl, capacity))
+        });
+
+        let shards_cache = self.shards_cache.map(|(ttl, capacity)| {
+            Arc::new(TtlCache::new("shards", ttl, capacity))
+        });
+
+        let send_message_dedup = self.send_message_dedup.map(|(ttl, capacity)| {
+            metrics::describe_counter!("ton_sendboc_dedup_hits_total", "Count of duplicate messages that were not broadcast again");
+
+            Arc::new(TtlCache::new("send_message_dedup", ttl, capacity))
+        });
+
+        let idempotency_keys = self.idempotency_keys.map(|(ttl, capacity)| {
+            metrics::describe_counter!("ton_sendboc_idempotency_hits_total", "Count of sends answered from a previously used idempotency key");
+
+            Arc::new(TtlCache::new("idempotency_keys", ttl, capacity))
+        });
+
+        let masterchain_info_cache = self.masterchain_info_cache.map(|ttl| {
+            Arc::new(SingleFlightCache::new("masterchain_info", ttl))
+        });
+
+        Ok(TonClient {
+            client,
+            archival,
+            raw_account_state_cache,
+            account_state_cache,
+            key_block_seqno_cache,
+            config_cache,
+            get_method_cache,
+            shards_cache,
+            send_message_dedup,
+            idempotency_keys,
+            masterchain_info_cache,
+            global_id: Default::default(),
+            synced,
+            network_head,
+            retry: retry_handle
+        })
     }
 }
 
@@ -203,16 +442,113 @@ impl IntoFuture for TonClientBuilder {
 impl TonClient {
     pub async fn ready(&mut self) -> anyhow::Result<()> {
         self.get_masterchain_info().await?;
+        self.synced.send_replace(true);
         tracing::info!("ready");
 
         Ok(())
     }
 
+    pub fn cache_stats(&self) -> Vec<CacheStats> {
+        self.caches().into_iter().map(|cache| cache.stats()).collect()
+    }
+
+    /// Returns false when there is no enabled cache named `name`.
+    pub fn flush_cache(&self, name: &str) -> bool {
+        let Some(cache) = self.caches().into_iter().find(|cache| cache.name() == name) else {
+            return false
+        };
+        cache.clear();
+        tracing::info!(cache = name, "cache flushed");
+
+        true
+    }
+
+    fn caches(&self) -> Vec<&dyn CacheControl> {
+        [
+            self.raw_account_state_cache.as_deref().map(|cache| cache as &dyn CacheControl),
+            self.account_state_cache.as_deref().map(|cache| cache as &dyn CacheControl),
+            self.key_block_seqno_cache.as_deref().map(|cache| cache as &dyn CacheControl),
+            self.config_cache.as_deref().map(|cache| cache as &dyn CacheControl),
+            self.get_method_cache.as_deref().map(|cache| cache as &dyn CacheControl),
+            self.shards_cache.as_deref().map(|cache| cache as &dyn CacheControl),
+            self.send_message_dedup.as_deref().map(|cache| cache as &dyn CacheControl),
+            self.idempotency_keys.as_deref().map(|cache| cache as &dyn CacheControl),
+            self.masterchain_info_cache.as_deref().map(|cache| cache as &dyn CacheControl)
+        ].into_iter().flatten().collect()
+    }
+
+    pub fn without_cache(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            archival: self.archival.clone(),
+            raw_account_state_cache: None,
+            account_state_cache: None,
+            key_block_seqno_cache: None,
+            config_cache: None,
+            get_method_cache: None,
+            shards_cache: None,
+            send_message_dedup: self.send_message_dedup.clone(),
+            idempotency_keys: self.idempotency_keys.clone(),
+            masterchain_info_cache: None,
+            global_id: self.global_id.clone(),
+            synced: self.synced.clone(),
+            network_head: self.network_head.clone(),
+            retry: self.retry.clone()
+        }
+    }
+
+    pub fn is_synced(&self) -> bool {
+        *self.synced.borrow()
+    }
+
+    pub fn retry_handle(&self) -> Option<RetryHandle> {
+        self.retry.clone()
+    }
+
+    /// `target_seqno` is the newest masterchain block any liteserver reported, `lag` is unknown when the header of the current block cannot be read.
+    pub async fn get_sync_status(&self) -> anyhow::Result<SyncStatus> {
+        let current = tokio::time::timeout(SYNC_CHECK_TIMEOUT, self.get_masterchain_info()).await
+            .ok()
+            .and_then(Result::ok);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let lag = match &current {
+            Some(info) => self.get_block_header_by_block_id(info.last.clone()).await
+                .ok()
+                .map(|header| Duration::from_secs(now.saturating_sub(header.gen_utime).max(0) as u64)),
+            None => None
+        };
+
+        let current_seqno = current.map(|info| info.last.seqno);
+        let network_head = self.network_head.load(Ordering::Relaxed);
+        let target_seqno = match (current_seqno, (network_head > 0).then_some(network_head)) {
+            (Some(current), Some(target)) => Some(current.max(target)),
+            (current, target) => target.or(current)
+        };
+
+        Ok(SyncStatus { synced: self.is_synced(), current_seqno, target_seqno, lag })
+    }
+
+    /// Served from the masterchain info cache when it is enabled, use `without_cache` for the latest block.
     pub async fn get_masterchain_info(&self) -> anyhow::Result<BlocksMasterchainInfo> {
-        self.client
+        let request = || self.client
             .clone()
-            .oneshot(Specialized::new(BlocksGetMasterchainInfo::default()))
-            .await
+            .oneshot(Specialized::new(BlocksGetMasterchainInfo::default()));
+
+        match &self.masterchain_info_cache {
+            Some(cache) => cache.get_or_try_refresh(request).await,
+            None => request().await
+        }
+    }
+
+    pub async fn get_global_id(&self) -> anyhow::Result<i32> {
+        self.global_id.get_or_try_init(|| async {
+            let last = self.get_masterchain_info().await?.last;
+
+            self.get_block_header_by_block_id(last)
+                .map_ok(|header| header.global_id)
+                .await
+        }).await.copied()
     }
 
     #[instrument(skip_all, err)]
@@ -248,15 +584,28 @@ impl TonClient {
             .await
     }
 
+    pub async fn look_up_block_by_utime(
+        &self,
+        chain: i32,
+        shard: i64,
+        utime: i32,
+    ) -> anyhow::Result<TonBlockIdExt> {
+        if utime <= 0 {
+            return Err(anyhow!("utime must be greater than 0"));
+        }
+
+        self.client
+            .clone()
+            .oneshot(BlocksLookupBlock::unixtime(TonBlockId::new(chain, shard, 0), utime))
+            .await
+    }
+
     pub async fn get_shards(&self, master_seqno: i32) -> anyhow::Result<BlocksShards> {
         let block = self
             .look_up_block_by_seqno(MAIN_CHAIN, MAIN_SHARD, master_seqno)
             .await?;
 
-        self.client
-            .clone()
-            .oneshot(BlocksGetShards::new(block))
-            .await
+        self.get_shards_on_block(block).await
     }
 
     pub async fn get_shards_by_block_id(&self, block_id: TonBlockIdExt) -> anyhow::Result<Vec<TonBlockIdExt>> {
@@ -264,10 +613,80 @@ impl TonClient {
             return Err(anyhow!("workchain must be -1"))
         }
 
+        self.get_shards_on_block(block_id)
+            .map_ok(|res| res.shards)
+            .await
+    }
+
+    async fn get_shards_on_block(&self, block_id: TonBlockIdExt) -> anyhow::Result<BlocksShards> {
+        let Some(cache) = &self.shards_cache else {
+            return self.client
+                .clone()
+                .oneshot(BlocksGetShards::new(block_id))
+                .await
+        };
+
+        if let Some(shards) = cache.get(&block_id) {
+            return Ok(shards);
+        }
+
+        let shards = self.client
+            .clone()
+            .oneshot(BlocksGetShards::new(block_id.clone()))
+            .await?;
+        cache.insert(block_id, shards.clone());
+
+        Ok(shards)
+    }
+
+    /// Proves masterchain block `to_seqno` against the trusted block `from_seqno`, tonlib only builds backward links.
+    /// When `to_seqno` is newer the links lead from it back to `from_seqno`, and the validator signatures of every key block
+    /// in between and of `to_seqno` itself, oldest first, let a client that trusts `from_seqno` advance to it.
+    /// Fails with `TooManyKeyBlocks` when more than `BLOCK_PROOF_MAX_KEY_BLOCKS` key blocks lie in between.
+    pub async fn get_block_proof(&self, from_seqno: i32, to_seqno: i32) -> anyhow::Result<BlockProof> {
+        let (from, to) = try_join!(
+            self.look_up_block_by_seqno(MAIN_CHAIN, MAIN_SHARD, from_seqno),
+            self.look_up_block_by_seqno(MAIN_CHAIN, MAIN_SHARD, to_seqno)
+        )?;
+
+        if from_seqno >= to_seqno {
+            let proof = self.client
+                .clone()
+                .oneshot(BlocksGetShardBlockProof::new(to, 1, Some(from)))
+                .await?;
+
+            return Ok(BlockProof { proof, signatures: vec![] })
+        }
+
+        let mut seqnos = vec![to_seqno];
+        let mut key_block_seqno = self.get_block_header_by_block_id(to.clone()).await?.prev_key_block_seqno;
+        while key_block_seqno > from_seqno {
+            if seqnos.len() > BLOCK_PROOF_MAX_KEY_BLOCKS {
+                return Err(TooManyKeyBlocks(BLOCK_PROOF_MAX_KEY_BLOCKS).into())
+            }
+            seqnos.push(key_block_seqno);
+            key_block_seqno = self.get_block_header(MAIN_CHAIN, MAIN_SHARD, key_block_seqno).await?.prev_key_block_seqno;
+        }
+
+        let (proof, signatures) = try_join!(
+            self.client
+                .clone()
+                .oneshot(BlocksGetShardBlockProof::new(from, 1, Some(to))),
+            futures::future::try_join_all(seqnos.into_iter().rev().map(|seqno| self.client
+                .clone()
+                .oneshot(BlocksGetMasterchainBlockSignatures::new(seqno))))
+        )?;
+
+        Ok(BlockProof { proof, signatures })
+    }
+
+    /// Links `block_id` to the masterchain block that commits it, and that one to `from` when it is given.
+    pub async fn get_shard_block_proof(&self, block_id: TonBlockIdExt, from: Option<TonBlockIdExt>) -> anyhow::Result<BlocksShardBlockProof> {
+        let mode = if from.is_some() { 1 } else { 0 };
+
         self.client
             .clone()
-            .oneshot(BlocksGetShards::new(block_id))
-            .map_ok(|res| res.shards)
+            .oneshot(BlocksGetShardBlockProof::new(block_id, mode, from))
             .await
     }
 
@@ -279,20 +698,62 @@ impl TonClient {
     ) -> anyhow::Result<BlocksHeader> {
         let id = self.look_up_block_by_seqno(chain, shard, seqno).await?;
 
+        self.get_block_header_by_block_id(id).await
+    }
+
+    pub async fn get_block_header_by_block_id(&self, block_id: TonBlockIdExt) -> anyhow::Result<BlocksHeader> {
         self.client
             .clone()
-            .oneshot(BlocksGetBlockHeader::new(id))
+            .oneshot(BlocksGetBlockHeader::new(block_id))
             .await
     }
 
     #[instrument(skip_all, err)]
     pub async fn raw_get_account_state(&self, address: &str) -> anyhow::Result<RawFullAccountState> {
         let account_address = AccountAddress::new(address)?;
+        let Some(cache) = self.raw_account_state_cache.as_ref() else {
+            return self.client
+                .clone()
+                .oneshot(RawGetAccountState::new(account_address))
+                .await
+        };
 
-        self.client
+        let key = AccountAddressData::from_str(address)?.to_raw_string();
+        if let Some(state) = cache.get(&key) {
+            return Ok(state);
+        }
+
+        let state = self.client
             .clone()
             .oneshot(RawGetAccountState::new(account_address))
-            .await
+            .await?;
+        cache.insert(key, state.clone());
+
+        Ok(state)
+    }
+
+    /// The state at least at the last masterchain block, served from the account state cache when it is enabled.
+    #[instrument(skip_all, err)]
+    pub async fn raw_get_account_state_at_least_last_block(&self, address: &str) -> anyhow::Result<RawFullAccountState> {
+        let key = match self.raw_account_state_cache {
+            Some(ref cache) => {
+                let key = AccountAddressData::from_str(address)?.to_raw_string();
+                if let Some(state) = cache.get(&key) {
+                    return Ok(state);
+                }
+
+                Some((cache, key))
+            },
+            None => None
+        };
+
+        let block_id = self.get_masterchain_info().await?.last;
+        let state = self.raw_get_account_state_at_least_block(address, &block_id).await?;
+        if let Some((cache, key)) = key {
+            cache.insert(key, state.clone());
+        }
+
+        Ok(state)
     }
 
     #[instrument(skip_all, err)]
@@ -328,11 +789,25 @@ impl TonClient {
 
     pub async fn get_account_state(&self, address: &str) -> anyhow::Result<FullAccountState> {
         let account_address = AccountAddress::new(address)?;
+        let Some(cache) = self.account_state_cache.as_ref() else {
+            return self.client
+                .clone()
+                .oneshot(GetAccountState::new(account_address))
+                .await
+        };
 
-        self.client
+        let key = AccountAddressData::from_str(address)?.to_raw_string();
+        if let Some(state) = cache.get(&key) {
+            return Ok(state);
+        }
+
+        let state = self.client
             .clone()
             .oneshot(GetAccountState::new(account_address))
-            .await
+            .await?;
+        cache.insert(key, state.clone());
+
+        Ok(state)
     }
 
     #[instrument(skip_all, err)]
@@ -342,11 +817,18 @@ impl TonClient {
         from_tx: &InternalTransactionId
     ) -> anyhow::Result<RawTransactions> {
         let address = AccountAddress::new(address)?;
+        let request = RawGetTransactionsV2::new(address, from_tx.clone(), 16, false);
 
-        self.client
-            .clone()
-            .oneshot(RawGetTransactionsV2::new(address, from_tx.clone(), 16, false))
-            .await
+        match self.client.clone().oneshot(request.clone()).await {
+            Err(e) if Error::is_too_deep(&e) => {
+                let Some(archival) = self.archival.clone() else { return Err(e) };
+                tracing::info!(lt = from_tx.lt, error = %e, "escalating getTransactions to archival liteservers");
+                metrics::counter!("ton_archival_escalations_total").increment(1);
+
+                archival.oneshot(request).await
+            },
+            result => result
+        }
     }
 
     pub async fn blocks_get_transactions_ext(
@@ -412,14 +894,152 @@ impl TonClient {
         Ok(())
     }
 
+    /// Duplicates are recognized by the message hash, tonlib rejects a message that is not a valid boc anyway.
     pub async fn send_message_returning_hash(&self, message: &str) -> anyhow::Result<String> {
-        self.client
+        let dedup = self.send_message_dedup.as_ref()
+            .and_then(|dedup| message_hash(message).ok().map(|key| (dedup, key)));
+        if let Some(hash) = dedup.as_ref().and_then(|(dedup, key)| dedup.get(key)) {
+            metrics::counter!("ton_sendboc_dedup_hits_total").increment(1);
+
+            return Ok(hash);
+        }
+
+        let hash = self.client
             .clone()
             .oneshot(RawSendMessageReturnHash::new(message.to_string()))
             .map_ok(|res| res.hash)
+            .await?;
+
+        if let Some((dedup, key)) = dedup {
+            dedup.insert(key, hash.clone());
+        }
+
+        Ok(hash)
+    }
+
+    /// Returns the hash recorded for `key` instead of sending again, fails with [`IdempotencyKeyReused`] when `key` was used for another message.
+    pub async fn send_message_with_idempotency_key(&self, key: &str, message: &str) -> anyhow::Result<String> {
+        let Some(idempotency_keys) = &self.idempotency_keys else {
+            return self.send_message_returning_hash(message).await
+        };
+        // tonlib rejects a message that is not a valid boc anyway
+        let Ok(message_hash) = message_hash(message) else {
+            return self.send_message_returning_hash(message).await
+        };
+
+        send_once(idempotency_keys, key, message_hash, || self.send_message_returning_hash(message)).await
+    }
+
+    /// Fees of processing `body` by `address`, `init_code` and `init_data` deploy the account when it isn't active.
+    pub async fn estimate_fee(&self, address: &str, body: &str, init_code: &str, init_data: &str, ignore_chksig: bool) -> anyhow::Result<QueryFees> {
+        let query = RawCreateQuery::new(AccountAddress::new(address)?, init_code.to_owned(), init_data.to_owned(), body.to_owned());
+
+        self.client
+            .clone()
+            .oneshot(EstimateFee::new(query, ignore_chksig))
             .await
     }
 
+    /// Polls `address` until a transaction for the external message with `body_hash` appears after `since`.
+    /// Without `since` only the latest page of transactions is searched on the first poll.
+    pub async fn wait_for_transaction(&self, address: &str, body_hash: &str, mut since: Option<InternalTransactionId>, poll_interval: Duration) -> anyhow::Result<RawTransaction> {
+        loop {
+            let state = self.without_cache().raw_get_account_state(address).await?;
+            if let Some(last_tx) = state.last_transaction_id.filter(|tx| since.as_ref() != Some(tx)) {
+                let stream = match since.clone() {
+                    Some(since) => self.get_account_tx_range(address, (Bound::Included(last_tx.clone()), Bound::Excluded(since))).boxed(),
+                    None => self.get_account_tx_range(address, (Bound::Included(last_tx.clone()), Bound::Unbounded)).take(16).boxed()
+                };
+
+                let found = stream
+                    .try_filter(|tx| std::future::ready(tx.in_msg.as_ref()
+                        .is_some_and(|msg| msg.source.account_address.is_none() && msg.body_hash == body_hash)))
+                    .try_next()
+                    .await?;
+                if let Some(tx) = found {
+                    return Ok(tx)
+                }
+
+                since = Some(last_tx);
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Finds the transaction of `source` that emitted the message to `destination` created at `created_lt`.
+    /// The emitting transaction is the last one of `source` before `created_lt`, it is read from the shard block holding that logical time.
+    pub async fn try_locate_source_tx(&self, source: &str, destination: &str, created_lt: i64) -> anyhow::Result<Option<RawTransaction>> {
+        let source_data = AccountAddressData::from_str(source)?;
+        let destination = AccountAddressData::from_str(destination)?.to_raw_string();
+
+        let block = self.look_up_block_by_lt(source_data.chain_id, source_data.shard_prefix(), created_lt).await?;
+        let state = self.raw_get_account_state_on_block(source, block).await?;
+        let Some(last_tx) = state.last_transaction_id else {
+            return Ok(None)
+        };
+
+        let tx = self.get_account_tx_stream_from(source, Some(last_tx))
+            .try_skip_while(|tx| std::future::ready(Ok(tx.transaction_id.lt >= created_lt)))
+            .try_next()
+            .await?;
+
+        Ok(tx.filter(|tx| tx.out_msgs.iter()
+            .any(|msg| msg.created_lt == created_lt && is_account(&msg.destination, &destination))))
+    }
+
+    /// Finds the transaction of `destination` that processed the message from `source` created at `created_lt`.
+    /// The message may wait in queues for several blocks, so up to `TRY_LOCATE_MAX_BLOCKS` blocks of the destination shard
+    /// are scanned forward from the one holding `created_lt`.
+    pub async fn try_locate_result_tx(&self, source: &str, destination: &str, created_lt: i64) -> anyhow::Result<Option<RawTransaction>> {
+        let source = AccountAddressData::from_str(source)?.to_raw_string();
+        let destination_data = AccountAddressData::from_str(destination)?;
+        let destination = destination_data.to_raw_string();
+
+        let mut block = self.look_up_block_by_lt(destination_data.chain_id, destination_data.shard_prefix(), created_lt).await?;
+        for _ in 0 .. TRY_LOCATE_MAX_BLOCKS {
+            let tx = self.get_block_tx_stream(&block, false)
+                .try_filter(|tx| std::future::ready(is_account(&tx.address, &destination) && tx.in_msg.as_ref()
+                    .is_some_and(|msg| msg.created_lt == created_lt && is_account(&msg.source, &source))))
+                .try_next()
+                .await?;
+            if tx.is_some() {
+                return Ok(tx)
+            }
+
+            let header = self.get_block_header_by_block_id(block).await?;
+            block = self.look_up_block_by_lt(destination_data.chain_id, destination_data.shard_prefix(), header.end_lt).await?;
+        }
+
+        Ok(None)
+    }
+
+    /// Yields every masterchain block starting at `from_seqno`, or after the one that was last when the stream was polled first.
+    /// Blocks produced between two polls are looked up so the stream has no gaps.
+    pub fn masterchain_block_stream(&self, from_seqno: Option<i32>, poll_interval: Duration) -> impl Stream<Item=anyhow::Result<TonBlockIdExt>> + 'static {
+        let client = self.clone();
+
+        async_stream::try_stream! {
+            let mut last = match from_seqno {
+                Some(seqno) => seqno - 1,
+                None => client.get_masterchain_info().await?.last.seqno
+            };
+            loop {
+                let current = client.get_masterchain_info().await?.last;
+                for seqno in last + 1 .. current.seqno {
+                    yield client.look_up_block_by_seqno(MAIN_CHAIN, MAIN_SHARD, seqno).await?;
+                }
+
+                if current.seqno > last {
+                    last = current.seqno;
+                    yield current;
+                } else {
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+
     pub fn get_block_tx_stream_unordered(&self, block: &TonBlockIdExt) -> impl Stream<Item=anyhow::Result<BlocksShortTxId>> + 'static {
         let streams = Side::values().map(move |side| {
             (side, self.get_block_tx_id_stream(block, side.is_right()).boxed())
@@ -446,6 +1066,15 @@ impl TonClient {
         &self,
         block: &TonBlockIdExt,
         reverse: bool
+    ) -> impl Stream<Item=anyhow::Result<RawTransaction>> + 'static {
+        self.get_block_tx_stream_from(block, None, reverse)
+    }
+
+    pub fn get_block_tx_stream_from(
+        &self,
+        block: &TonBlockIdExt,
+        after: Option<BlocksAccountTransactionId>,
+        reverse: bool
     ) -> impl Stream<Item=anyhow::Result<RawTransaction>> + 'static {
         struct State {
             last_tx: Option<BlocksAccountTransactionId>,
@@ -457,7 +1086,7 @@ impl TonClient {
 
         stream::try_unfold(
             State {
-                last_tx: None,
+                last_tx: after,
                 incomplete: true,
                 block: block.clone(),
                 this: self.clone(),
@@ -496,6 +1125,15 @@ impl TonClient {
         &self,
         block: &TonBlockIdExt,
         reverse: bool
+    ) -> impl Stream<Item=anyhow::Result<BlocksShortTxId>> + 'static {
+        self.get_block_tx_id_stream_from(block, None, reverse)
+    }
+
+    pub fn get_block_tx_id_stream_from(
+        &self,
+        block: &TonBlockIdExt,
+        after: Option<BlocksAccountTransactionId>,
+        reverse: bool
     ) -> impl Stream<Item=anyhow::Result<BlocksShortTxId>> + 'static {
         struct State {
             last_tx: Option<BlocksAccountTransactionId>,
@@ -507,7 +1145,7 @@ impl TonClient {
 
         stream::try_unfold(
             State {
-                last_tx: None,
+                last_tx: after,
                 incomplete: true,
                 block: block.clone(),
                 this: self.clone(),
@@ -560,7 +1198,7 @@ impl TonClient {
                 let last_tx = match range.start_bound().cloned() {
                     Bound::Included(tx) | Bound::Excluded(tx) => tx.to_owned(),
                     Bound::Unbounded => {
-                        let state = self.raw_get_account_state(address).await?;
+                        let state = self.without_cache().raw_get_account_state(address).await?;
 
                         state.last_transaction_id.ok_or_else(|| anyhow!("invalid last tx"))?
                     },
@@ -675,7 +1313,7 @@ impl TonClient {
             }
 
             let next_id = if let Some(id) = state.next_id { id } else {
-                let state = state.this.raw_get_account_state(&state.address).await?;
+                let state = state.this.without_cache().raw_get_account_state(&state.address).await?;
                 let Some(tx_id) = state.last_transaction_id else {
                     return anyhow::Ok(None);
                 };
@@ -702,13 +1340,92 @@ impl TonClient {
         }).try_flatten()
     }
 
-    pub async fn run_get_method(&self, address: String, method: String, stack: Vec<TvmBoxedStackEntry>) -> anyhow::Result<SmcRunResult> {
+    pub async fn run_get_method(&self, address: String, method: impl Into<SmcBoxedMethodId>, stack: Vec<TvmBoxedStackEntry>) -> anyhow::Result<SmcRunResult> {
+        let method = method.into();
+        let Some(cache) = &self.get_method_cache else {
+            return self.run_get_method_uncached(&address, method, stack).await
+        };
+
+        // the get-method runs on the block of the fetched state, so the result always matches the key
+        let state = self.raw_get_account_state(&address).await?;
+        let Some(last_transaction_id) = state.last_transaction_id else {
+            return self.run_get_method_on_block(address, method, stack, state.block_id).await
+        };
+
+        let key = (
+            AccountAddressData::from_str(&address)?.to_raw_string(),
+            serde_json::to_string(&method)?,
+            serde_json::to_string(&stack)?,
+            last_transaction_id.lt
+        );
+        if let Some(result) = cache.get(&key) {
+            return Ok(result);
+        }
+
+        let result = self.run_get_method_on_block(address, method, stack, state.block_id).await?;
+        cache.insert(key, result.clone());
+
+        Ok(result)
+    }
+
+    pub async fn run_get_method_on_block(&self, address: String, method: impl Into<SmcBoxedMethodId>, stack: Vec<TvmBoxedStackEntry>, block_id: TonBlockIdExt) -> anyhow::Result<SmcRunResult> {
         let address = AccountAddress::new(&address)?;
-        let method = SmcBoxedMethodId::by_name(&method);
 
         self.client
             .clone()
-            .oneshot(RunGetMethod::new(address, method, stack))
+            .oneshot(RunGetMethod::new(address, method.into(), stack, Some(block_id)))
+            .await
+    }
+
+    async fn run_get_method_uncached(&self, address: &str, method: SmcBoxedMethodId, stack: Vec<TvmBoxedStackEntry>) -> anyhow::Result<SmcRunResult> {
+        let address = AccountAddress::new(address)?;
+
+        self.client
+            .clone()
+            .oneshot(RunGetMethod::new(address, method, stack, None))
+            .await
+    }
+
+    pub async fn get_config_param(&self, param: i32) -> anyhow::Result<TvmCell> {
+        let (Some(key_block_seqno_cache), Some(config_cache)) = (&self.key_block_seqno_cache, &self.config_cache) else {
+            return self.client
+                .clone()
+                .oneshot(GetConfigParam::new(0, param))
+                .map_ok(|info| info.config)
+                .await
+        };
+
+        let last = self.get_masterchain_info().await?.last;
+        let key_block_seqno = match key_block_seqno_cache.get(&last.seqno) {
+            Some(seqno) => seqno,
+            None => {
+                let header = self.get_block_header_by_block_id(last.clone()).await?;
+                let seqno = if header.is_key_block { header.id.seqno } else { header.prev_key_block_seqno };
+                key_block_seqno_cache.insert(last.seqno, seqno);
+
+                seqno
+            }
+        };
+
+        if let Some(config) = config_cache.get(&(param, key_block_seqno)) {
+            return Ok(config);
+        }
+
+        let config = self.client
+            .clone()
+            .oneshot(WithBlock::new(last, GetConfigParam::new(0, param)))
+            .map_ok(|info| info.config)
+            .await?;
+        config_cache.insert((param, key_block_seqno), config.clone());
+
+        Ok(config)
+    }
+
+    pub async fn get_config_param_on_block(&self, block_id: TonBlockIdExt, param: i32) -> anyhow::Result<TvmCell> {
+        self.client
+            .clone()
+            .oneshot(WithBlock::new(block_id, GetConfigParam::new(0, param)))
+            .map_ok(|info| info.config)
             .await
     }
 
@@ -829,3 +1546,101 @@ impl TonClient {
         state.last_transaction_id.ok_or(anyhow!("tx not found"))
     }
 }
+
+fn is_account(address: &AccountAddress, raw: &str) -> bool {
+    address.account_address.as_deref()
+        .and_then(|address| AccountAddressData::from_str(address).ok())
+        .is_some_and(|address| address.to_raw_string() == raw)
+}
+
+/// The hash of the root cell, the same for every base64 alphabet and boc serialization of the message.
+fn message_hash(message: &str) -> anyhow::Result<String> {
+    let bytes = STANDARD.decode(message)
+        .or_else(|e| URL_SAFE.decode(message).or_else(|_| URL_SAFE_NO_PAD.decode(message)).map_err(|_| e))?;
+    let boc: BoC = unpack_bytes(bytes)
+        .map_err(|e| anyhow!("invalid boc: {}", e))?;
+    let root = boc.single_root()
+        .ok_or_else(|| anyhow!("boc must contain a single root cell"))?;
+
+    Ok(hex::encode(root.hash()))
+}
+
+/// Reserves `key` for the message with `message_hash` before sending, so concurrent retries wait for the first send.
+/// A failed send leaves the key reserved for the same message, the next retry sends again.
+async fn send_once<F, Fut>(keys: &TtlCache<String, (String, Arc<tokio::sync::OnceCell<String>>)>, key: &str, message_hash: String, send: F) -> anyhow::Result<String>
+    where F: FnOnce() -> Fut, Fut: Future<Output=anyhow::Result<String>> {
+    let (recorded, sent) = keys.get_or_insert(key.to_owned(), (message_hash.clone(), Default::default()));
+    if recorded != message_hash {
+        return Err(IdempotencyKeyReused.into());
+    }
+    if let Some(hash) = sent.get() {
+        metrics::counter!("ton_sendboc_idempotency_hits_total").increment(1);
+
+        return Ok(hash.clone());
+    }
+
+    sent.get_or_try_init(send).await.cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+    use toner::tlb::bits::ser::pack_with;
+    use toner::tlb::Cell;
+    use toner::ton::boc::{BagOfCellsArgs, BoC};
+    use crate::cache::TtlCache;
+    use crate::ton::{message_hash, send_once, IdempotencyKeyReused};
+
+    #[test]
+    fn message_hash_ignores_encoding() {
+        let root = Arc::new(Cell::default());
+        let plain = pack_with(BoC::from_root(root.clone()), BagOfCellsArgs { has_idx: false, has_crc32c: false }).unwrap();
+        let with_crc = pack_with(BoC::from_root(root), BagOfCellsArgs { has_idx: false, has_crc32c: true }).unwrap();
+
+        let hash = message_hash(&STANDARD.encode(plain.as_raw_slice())).unwrap();
+
+        assert_eq!(message_hash(&URL_SAFE_NO_PAD.encode(plain.as_raw_slice())).unwrap(), hash);
+        assert_eq!(message_hash(&STANDARD.encode(with_crc.as_raw_slice())).unwrap(), hash);
+    }
+
+    #[tokio::test]
+    async fn send_once_sends_concurrent_retries_once() {
+        let keys = TtlCache::new("test", Duration::from_secs(60), 16);
+        let sends = AtomicU32::new(0);
+        let send = || async {
+            sends.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+
+            anyhow::Ok("hash".to_owned())
+        };
+
+        let (a, b) = tokio::join!(
+            send_once(&keys, "key", "message".to_owned(), send),
+            send_once(&keys, "key", "message".to_owned(), send)
+        );
+
+        assert_eq!((a.unwrap(), b.unwrap()), ("hash".to_owned(), "hash".to_owned()));
+        assert_eq!(sends.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn send_once_rejects_key_of_another_message() {
+        let keys = TtlCache::new("test", Duration::from_secs(60), 16);
+
+        send_once(&keys, "key", "message".to_owned(), || async { anyhow::Ok("hash".to_owned()) }).await.unwrap();
+        let e = send_once(&keys, "key", "other".to_owned(), || async { anyhow::Ok("other".to_owned()) }).await.unwrap_err();
+
+        assert!(e.is::<IdempotencyKeyReused>());
+    }
+
+    #[tokio::test]
+    async fn send_once_sends_again_after_failure() {
+        let keys = TtlCache::new("test", Duration::from_secs(60), 16);
+
+        assert!(send_once(&keys, "key", "message".to_owned(), || async { Err(anyhow::anyhow!("unavailable")) }).await.is_err());
+        assert_eq!(send_once(&keys, "key", "message".to_owned(), || async { anyhow::Ok("hash".to_owned()) }).await.unwrap(), "hash");
+    }
+}
@@ -1,6 +1,7 @@
 use std::env;
 use std::fs::{create_dir_all, copy};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use cmake::Config;
 use walkdir::WalkDir;
@@ -33,6 +34,17 @@ fn main() {
         "ton"
     };
     eprintln!("ton dir is {}", ton_dir);
+
+    let tonlib_version = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(ton_dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned());
+    println!("cargo:rustc-env=TONLIB_VERSION={tonlib_version}");
     println!("cargo:rerun-if-changed={ton_dir}/CMakeLists.txt");
     println!("cargo:rerun-if-changed=build.rs");
 
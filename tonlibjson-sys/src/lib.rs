@@ -1,3 +1,5 @@
+pub const TONLIB_VERSION: &str = env!("TONLIB_VERSION");
+
 #[cfg(feature = "tonlibjson")]
 mod tonlibjson;
 #[cfg(feature = "tonemulator")]
@@ -3,6 +3,7 @@ mod transaction_emulator;
 mod tvm_emulator;
 mod threaded;
 
+use std::num::NonZeroUsize;
 use std::net::SocketAddr;
 use std::time::Duration;
 use clap::Parser;
@@ -22,8 +23,12 @@ use crate::tvm_emulator::TvmEmulatorService;
 struct Args {
     #[clap(long, default_value = "0.0.0.0:50052")]
     listen: SocketAddr,
+    #[clap(long, env = "WORKER_THREADS")]
+    worker_threads: Option<NonZeroUsize>,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "30s")]
     timeout: Duration,
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "30s")]
+    shutdown_timeout: Duration,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "300s")]
     tcp_keepalive: Duration,
     #[clap(long, value_parser = humantime::parse_duration, default_value = "120s")]
@@ -36,10 +41,21 @@ struct Args {
     initial_stream_window_size: u32,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
+    let runtime = match args.worker_threads {
+        Some(worker_threads) => tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(worker_threads.get())
+            .enable_all()
+            .build()?,
+        None => tokio::runtime::Runtime::new()?
+    };
+
+    runtime.block_on(run(args))
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
     TvmEmulator::set_verbosity_level(0);
 
     tracing_subscriber::fmt()
@@ -47,6 +63,10 @@ async fn main() -> anyhow::Result<()> {
         .with_span_events(FmtSpan::CLOSE)
         .init();
 
+    if let Some(worker_threads) = args.worker_threads {
+        tracing::info!("Running with {} worker threads", worker_threads);
+    }
+
     let reflection = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
         .register_encoded_file_descriptor_set(tvm::FILE_DESCRIPTOR_SET)
@@ -66,7 +86,15 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("Listening on {:?}", &args.listen);
 
-    Server::builder()
+    let (shutdown_tx, mut shutdown_rx) = tokio::sync::watch::channel(false);
+    let shutdown = async move {
+        ton_client_utils::shutdown::signal().await;
+
+        tracing::info!("Shutting down");
+        let _ = shutdown_tx.send(true);
+    };
+
+    let server = Server::builder()
         .timeout(args.timeout)
         .tcp_keepalive(args.tcp_keepalive.into())
         .http2_keepalive_interval(args.http2_keepalive_interval.into())
@@ -79,8 +107,15 @@ async fn main() -> anyhow::Result<()> {
         .add_service(tvm_emulator_service)
         .add_service(transaction_emulator_service)
 
-        .serve_with_shutdown(args.listen, async move { tokio::signal::ctrl_c().await.unwrap(); })
-        .await?;
+        .serve_with_shutdown(args.listen, shutdown);
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => result?,
+        _ = async { let _ = shutdown_rx.wait_for(|shutdown| *shutdown).await; tokio::time::sleep(args.shutdown_timeout).await; } => {
+            tracing::warn!("Shutdown timeout of {:?} exceeded", args.shutdown_timeout);
+        }
+    }
 
     Ok(())
 }